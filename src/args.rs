@@ -6,13 +6,50 @@ pub fn get_day_part() -> (u8, u8) {
         .unwrap_or("1".to_string())
         .parse()
         .expect("Failed to parse day arg");
-    let mut part: u8 = env::args()
-        .nth(2)
-        .unwrap_or("1".to_string())
-        .parse()
-        .expect("Failed to parse part arg");
+    let mut part: u8 = match get_command() {
+        Some(_) => 1,
+        None => env::args()
+            .nth(2)
+            .unwrap_or("1".to_string())
+            .parse()
+            .expect("Failed to parse part arg"),
+    };
     if part != 2 {
         part = 1;
     }
     return (day, part);
 }
+
+/**
+ * A command given in place of PART (anything that isn't a number), e.g. "query"
+ */
+pub fn get_command() -> Option<String> {
+    let arg = env::args().nth(2)?;
+    if arg.parse::<u8>().is_ok() {
+        return None;
+    }
+    return Some(arg);
+}
+
+// Positional arg by index (1 is DAY), if given
+pub fn get_arg(index: usize) -> Option<String> {
+    return env::args().nth(index);
+}
+
+/**
+ * Look up an option given after DAY PART, as either --name or --name=value
+ * Returns the value (empty for a bare flag), or None if not given
+ */
+pub fn get_option(name: &str) -> Option<String> {
+    let flag = format!("--{name}");
+    for arg in env::args().skip(3) {
+        if arg == flag {
+            return Some("".to_string());
+        }
+        match arg.strip_prefix(&flag) {
+            Some(rest) if rest.starts_with('=') => return Some(rest[1..].to_string()),
+            _ => (),
+        }
+    }
+    return None;
+}
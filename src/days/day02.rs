@@ -1,24 +1,39 @@
+use crate::args;
+use crate::num;
+use crate::output;
+
 const DEBUG_OUTPUT: bool = false;
 
-fn count_invalid_in_range_part1(left: u64, right: u64) -> u64 {
+// An invalid ID, made up of a chunk of digits repeated a number of times
+struct InvalidId {
+    id: u64,
+    chunk: String,
+    repeats: usize,
+}
+
+fn invalid_in_range_part1(left: u64, right: u64, radix: u32) -> Vec<InvalidId> {
     if DEBUG_OUTPUT {
         println!("Range: {left}-{right}");
     }
-    let mut sum_invalid_ids = 0;
-    let mut invalid_ids = 0;
+    let mut invalid_ids = vec![];
     for i in left..=right {
-        let i_str = i.to_string();
+        let i_str = num::to_radix_string(i, radix);
         let len = i_str.len();
         if i_str.len() % 2 != 0 {
             continue;
         }
         if i_str[0..len / 2] == i_str[len / 2..] {
-            invalid_ids += 1;
-            sum_invalid_ids += i;
-            println!("    ({invalid_ids}): {i_str}");
+            if DEBUG_OUTPUT {
+                println!("    ({}): {i_str}", invalid_ids.len() + 1);
+            }
+            invalid_ids.push(InvalidId {
+                id: i,
+                chunk: i_str[0..len / 2].to_string(),
+                repeats: 2,
+            });
         }
     }
-    return sum_invalid_ids;
+    return invalid_ids;
 }
 
 fn chunk_repeated(chunk: &str, remnant: &str) -> bool {
@@ -43,14 +58,13 @@ fn chunk_repeated(chunk: &str, remnant: &str) -> bool {
     return chunk_repeated(chunk, &remnant[chunk.len()..]);
 }
 
-fn count_invalid_in_range_part2(left: u64, right: u64) -> u64 {
+fn invalid_in_range_part2(left: u64, right: u64, radix: u32) -> Vec<InvalidId> {
     if DEBUG_OUTPUT {
         println!("Range: {left}-{right}");
     }
-    let mut sum_invalid_ids = 0;
-    let mut invalid_ids = 0;
+    let mut invalid_ids = vec![];
     for i in left..=right {
-        let i_str = i.to_string();
+        let i_str = num::to_radix_string(i, radix);
         let len = i_str.len();
         if DEBUG_OUTPUT {
             println!("  i: {i} ({len})");
@@ -62,20 +76,23 @@ fn count_invalid_in_range_part2(left: u64, right: u64) -> u64 {
                 print!("    chunk '{chunk}': ");
             }
             if chunk_repeated(chunk, &i_str[chunk_len..]) {
-                invalid_ids += 1;
-                sum_invalid_ids += i;
                 if DEBUG_OUTPUT {
-                    println!("    ({invalid_ids}): {i_str}");
+                    println!("    ({}): {i_str}", invalid_ids.len() + 1);
                 }
+                invalid_ids.push(InvalidId {
+                    id: i,
+                    chunk: chunk.to_string(),
+                    repeats: len / chunk_len,
+                });
                 break;
             }
         }
     }
-    return sum_invalid_ids;
+    return invalid_ids;
 }
 
-fn count_invalid_ids(lines: Vec<String>, part: u8) -> u64 {
-    let mut sum_invalid_ids = 0;
+fn find_invalid_ids(lines: Vec<String>, part: u8, radix: u32) -> Vec<InvalidId> {
+    let mut invalid_ids = vec![];
     for line in lines {
         if line == "" {
             continue;
@@ -83,25 +100,65 @@ fn count_invalid_ids(lines: Vec<String>, part: u8) -> u64 {
         let ranges = line.split(",");
         for range in ranges {
             let sides: Vec<&str> = range.split("-").collect();
-            let left: u64 = sides[0].parse().expect("Bad int on left");
-            let right: u64 = sides[1].parse().expect("Bad int on right");
-            sum_invalid_ids += match part {
-                1 => count_invalid_in_range_part1(left, right),
-                2 => count_invalid_in_range_part2(left, right),
+            let left = u64::from_str_radix(sides[0], radix).expect("Bad int on left");
+            let right = u64::from_str_radix(sides[1], radix).expect("Bad int on right");
+            invalid_ids.append(&mut match part {
+                1 => invalid_in_range_part1(left, right, radix),
+                2 => invalid_in_range_part2(left, right, radix),
                 _ => panic!("Invalid part"),
-            }
+            });
         }
     }
+    return invalid_ids;
+}
+
+// One line per invalid ID, e.g. "123123123: 123 x 3", with the ID written in the given radix
+fn list_invalid_ids(invalid_ids: &Vec<InvalidId>, radix: u32) -> String {
+    let mut list = String::new();
+    for inv in invalid_ids {
+        let id = num::to_radix_string(inv.id, radix);
+        list.push_str(&format!("{id}: {} x {}\n", inv.chunk, inv.repeats));
+    }
+    return list;
+}
+
+// Sum the invalid IDs, listing them to stdout or a file if a list path is given
+fn count_invalid_ids(lines: Vec<String>, part: u8, radix: u32, list_path: Option<String>) -> u64 {
+    let invalid_ids = find_invalid_ids(lines, part, radix);
+    if let Some(path) = list_path {
+        output::emit(&path, &list_invalid_ids(&invalid_ids, radix));
+    }
+    let sum_invalid_ids = invalid_ids.iter().map(|inv| inv.id).sum();
     println!("Total: {sum_invalid_ids}");
     return sum_invalid_ids;
 }
 
+fn get_radix() -> u32 {
+    let radix: u32 = match args::get_option("radix") {
+        Some(value) => value.parse().expect("Failed to parse radix"),
+        None => 10,
+    };
+    if !(2..=36).contains(&radix) {
+        panic!("Radix must be 2-36, got {radix}");
+    }
+    return radix;
+}
+
+/**
+ * Options:
+ *   --list[=FILE]  List each invalid ID with its repeating chunk, to stdout or FILE
+ *   --radix=N      Read ranges and look for repeated chunks in base N (2-36, default 10)
+ */
+fn run(lines: Vec<String>, part: u8) {
+    count_invalid_ids(lines, part, get_radix(), args::get_option("list"));
+}
+
 pub fn part1(lines: Vec<String>) {
-    count_invalid_ids(lines, 1);
+    run(lines, 1);
 }
 
 pub fn part2(lines: Vec<String>) {
-    count_invalid_ids(lines, 2);
+    run(lines, 2);
 }
 
 #[cfg(test)]
@@ -110,7 +167,52 @@ mod tests {
 
     #[test]
     pub fn test_count_invalid_ids() {
-        assert_eq!(count_invalid_ids(vec!["10-30".to_string()], 1), 11 + 22);
-        assert_eq!(count_invalid_ids(vec!["90-120".to_string()], 2), 99 + 111);
+        assert_eq!(
+            count_invalid_ids(vec!["10-30".to_string()], 1, 10, None),
+            11 + 22
+        );
+        assert_eq!(
+            count_invalid_ids(vec!["90-120".to_string()], 2, 10, None),
+            99 + 111
+        );
+    }
+
+    #[test]
+    pub fn test_list_invalid_ids() {
+        let invalid_ids =
+            find_invalid_ids(vec!["1010-1012,123123123-123123123".to_string()], 2, 10);
+        assert_eq!(
+            list_invalid_ids(&invalid_ids, 10),
+            "1010: 10 x 2\n123123123: 123 x 3\n".to_string()
+        );
+        let invalid_ids = find_invalid_ids(vec!["1111-1111".to_string()], 1, 10);
+        assert_eq!(
+            list_invalid_ids(&invalid_ids, 10),
+            "1111: 11 x 2\n".to_string()
+        );
+    }
+
+    #[test]
+    pub fn test_radix() {
+        // Binary: 10-15 is 1010-1111, where 1010 (10) and 1111 (15) repeat
+        assert_eq!(
+            count_invalid_ids(vec!["1010-1111".to_string()], 1, 2, None),
+            10 + 15
+        );
+        // Binary 111 (7) is only invalid in part 2
+        assert_eq!(
+            count_invalid_ids(vec!["101-111".to_string()], 2, 2, None),
+            7
+        );
+        // Hex: aa (170) and bb (187)
+        assert_eq!(
+            count_invalid_ids(vec!["a0-bf".to_string()], 1, 16, None),
+            170 + 187
+        );
+        let invalid_ids = find_invalid_ids(vec!["ababab-ababab".to_string()], 2, 16);
+        assert_eq!(
+            list_invalid_ids(&invalid_ids, 16),
+            "ababab: ab x 3\n".to_string()
+        );
     }
 }
@@ -1,90 +1,158 @@
+use crate::args;
+
 const DEBUG_OUTPUT: bool = false;
 
-fn max_digit(line: &str) -> (usize, u8) {
-    let mut max_digit = 0;
-    let mut pos = 0;
-    for i in 0..line.len() {
-        let digit: u8 = line[i..i + 1].parse().expect("Must be a number");
-        if digit > max_digit {
-            pos = i;
-            max_digit = digit;
+/**
+ * Positions of the k batteries in the bank which give the highest joltage
+ * (or the lowest, if highest is false), i.e. the lexicographically largest
+ * (or smallest) k-digit subsequence of the line.
+ *
+ * Uses a monotonic stack: a digit knocks smaller (or larger) digits off the
+ * top of the stack while there are still enough digits left to make up k.
+ */
+fn select_batteries(line: &str, k: usize, highest: bool) -> Result<Vec<usize>, String> {
+    let digits = line.as_bytes();
+    if k > digits.len() {
+        return Err(format!("Bank '{line}' has fewer than {k} batteries"));
+    }
+    let mut can_drop = digits.len() - k;
+    let mut stack: Vec<usize> = vec![];
+    for pos in 0..digits.len() {
+        if !digits[pos].is_ascii_digit() {
+            return Err(format!("Bank '{line}' has a battery that isn't a digit"));
+        }
+        while can_drop > 0 && stack.len() > 0 {
+            let top = digits[*stack.last().unwrap()];
+            let replace = if highest {
+                top < digits[pos]
+            } else {
+                top > digits[pos]
+            };
+            if !replace {
+                break;
+            }
+            stack.pop();
+            can_drop -= 1;
         }
+        stack.push(pos);
     }
+    stack.truncate(k);
     if DEBUG_OUTPUT {
-        println!("    pos: {pos}, max_digit: {max_digit} ({line})");
+        println!("    Line: {line}, positions: {:?}", stack);
     }
-    return (pos, max_digit);
+    return Ok(stack);
 }
 
-// Highest joltage for 2 batteries in bank
-fn highest_joltage(line: &str) -> u8 {
-    if DEBUG_OUTPUT {
-        println!("Line: {line}");
+// Joltage of the batteries at the given positions, or an error if it doesn't fit in a u128
+fn joltage(line: &str, positions: &Vec<usize>) -> Result<u128, String> {
+    let digits = line.as_bytes();
+    let mut joltage: u128 = 0;
+    for pos in positions {
+        joltage = joltage
+            .checked_mul(10)
+            .and_then(|j| j.checked_add(u128::from(digits[*pos] - b'0')))
+            .ok_or(format!(
+                "Joltage overflow for {} batteries in bank '{line}'",
+                positions.len()
+            ))?;
     }
-    let (start, digit1) = max_digit(&line[0..line.len() - 1]);
-    let (_, digit2) = max_digit(&line[start + 1..]);
     if DEBUG_OUTPUT {
-        println!("    Joltage: {digit1}{digit2}");
+        println!("    Joltage: {joltage}");
     }
-    return (digit1 * 10) + digit2;
+    return Ok(joltage);
 }
 
-// Highest joltage for 12 batteries in bank
-fn highest_joltage_12(line: &str) -> u64 {
-    let mut digits = vec![];
-    let mut digit_pos = 0;
-    if DEBUG_OUTPUT {
-        println!("Line: {line} {:}", line.len());
-    }
-    for step in 0..12 {
-        let remaining_digits = 11 - digits.len();
-        if DEBUG_OUTPUT {
-            println!("    step {step}: {digit_pos} -- {}", &line[digit_pos..]);
-            // println!("    use {step}: {}", &line[digit_pos..]);
-        }
-        let (offset, digit) = max_digit(&line[digit_pos..line.len() - remaining_digits]);
-        if DEBUG_OUTPUT {
-            println!("    digit at {offset}: {digit}");
+// Highest joltage for k batteries in bank
+fn highest_joltage(line: &str, k: usize) -> Result<u128, String> {
+    return joltage(line, &select_batteries(line, k, true)?);
+}
+
+// Bank with the batteries at the given positions highlighted in bold green
+fn highlight_bank(line: &str, positions: &Vec<usize>) -> String {
+    let mut rendered = String::new();
+    for (pos, ch) in line.chars().enumerate() {
+        if positions.contains(&pos) {
+            rendered.push_str(&format!("\x1b[1;32m{ch}\x1b[0m"));
+        } else {
+            rendered.push(ch);
         }
-        digits.push(digit);
-        digit_pos = digit_pos + offset + 1;
     }
+    return rendered;
+}
 
-    if DEBUG_OUTPUT {
-        println!("    Joltage: {:?}", digits);
-    }
-    let mut joltage = 0;
-    let mut exp = 0;
-    while digits.len() > 0 {
-        let digit = digits.pop().unwrap();
-        joltage += 10u64.pow(exp) * u64::from(digit);
-        exp += 1;
+// Describe the highest and lowest selections of k batteries in each bank
+fn explain(lines: &Vec<String>, k: usize) -> String {
+    let mut explanation = String::new();
+    for (line_num, line) in lines.iter().enumerate() {
+        if line == "" {
+            continue;
+        }
+        explanation.push_str(&format!("Bank {}: {line}\n", line_num + 1));
+        for (label, highest) in [("Highest", true), ("Lowest", false)] {
+            let positions = match select_batteries(line, k, highest) {
+                Ok(positions) => positions,
+                Err(err) => {
+                    explanation.push_str(&format!("    {label}: {err}\n"));
+                    continue;
+                }
+            };
+            let joltage = match joltage(line, &positions) {
+                Ok(joltage) => joltage.to_string(),
+                Err(err) => err,
+            };
+            explanation.push_str(&format!("    {label}: {joltage} at {:?}\n", positions));
+            explanation.push_str(&format!("        {}\n", highlight_bank(line, &positions)));
+        }
     }
-    return joltage;
+    return explanation;
 }
 
-pub fn part1(lines: Vec<String>) {
+fn total_joltage(lines: Vec<String>, k: usize) -> Result<u128, String> {
     let num_lines = lines.len();
     if DEBUG_OUTPUT {
         println!("{num_lines} line(s)");
     }
-    let mut total_joltage: u32 = 0;
-    for line in lines {
-        total_joltage += u32::from(highest_joltage(&line));
+    let mut total_joltage: u128 = 0;
+    for (line_num, line) in lines.iter().enumerate() {
+        if line == "" {
+            continue;
+        }
+        total_joltage = total_joltage
+            .checked_add(highest_joltage(line, k)?)
+            .ok_or(format!("Total joltage overflow at line {}", line_num + 1))?;
     }
-    println!("Total joltage: {total_joltage}");
+    return Ok(total_joltage);
 }
 
-pub fn part2(lines: Vec<String>) {
-    let num_lines = lines.len();
-    if DEBUG_OUTPUT {
-        println!("{num_lines} line(s)");
+fn print_total_joltage(lines: Vec<String>, k: usize) {
+    match total_joltage(lines, k) {
+        Ok(total_joltage) => println!("Total joltage: {total_joltage}"),
+        Err(err) => println!("Error: {err}"),
     }
-    let mut total_joltage = 0;
-    for line in lines {
-        total_joltage += highest_joltage_12(&line);
+}
+
+/**
+ * Options:
+ *   --k=N      Number of batteries to turn on in each bank (default 2 for part 1, 12 for part 2)
+ *   --explain  Show the batteries chosen in each bank, alongside the lowest joltage choice
+ */
+fn run(lines: Vec<String>, default_k: usize) {
+    let k = match args::get_option("k") {
+        Some(value) => value.parse().expect("Failed to parse k"),
+        None => default_k,
+    };
+    if args::get_option("explain").is_some() {
+        print!("{}", explain(&lines, k));
     }
-    println!("Total joltage: {total_joltage}");
+    print_total_joltage(lines, k);
+}
+
+pub fn part1(lines: Vec<String>) {
+    run(lines, 2);
+}
+
+pub fn part2(lines: Vec<String>) {
+    run(lines, 12);
 }
 
 #[cfg(test)]
@@ -93,17 +161,92 @@ mod tests {
 
     #[test]
     fn test_highest_joltage() {
-        assert_eq!(highest_joltage("12345"), 45);
-        assert_eq!(highest_joltage("54321"), 54);
-        assert_eq!(highest_joltage("92468"), 98);
-        assert_eq!(highest_joltage("919293"), 99);
-        assert_eq!(highest_joltage("9999"), 99);
-        assert_eq!(highest_joltage("212121"), 22);
-        assert_eq!(highest_joltage("97423391"), 99);
+        assert_eq!(highest_joltage("12345", 2), Ok(45));
+        assert_eq!(highest_joltage("54321", 2), Ok(54));
+        assert_eq!(highest_joltage("92468", 2), Ok(98));
+        assert_eq!(highest_joltage("919293", 2), Ok(99));
+        assert_eq!(highest_joltage("9999", 2), Ok(99));
+        assert_eq!(highest_joltage("212121", 2), Ok(22));
+        assert_eq!(highest_joltage("97423391", 2), Ok(99));
     }
 
     #[test]
     fn test_highest_joltage12() {
-        assert_eq!(highest_joltage_12("234234234234278"), 434234234278);
+        assert_eq!(highest_joltage("234234234234278", 12), Ok(434234234278));
+    }
+
+    #[test]
+    fn test_highest_joltage_k() {
+        assert_eq!(highest_joltage("12345", 1), Ok(5));
+        assert_eq!(highest_joltage("12345", 5), Ok(12345));
+        assert_eq!(highest_joltage("4321234", 3), Ok(434));
+        assert_eq!(highest_joltage("818181911112111", 12), Ok(888911112111));
+        assert_eq!(select_batteries("92468", 2, true), Ok(vec![0, 4]));
+    }
+
+    #[test]
+    fn test_short_bank() {
+        assert_eq!(
+            highest_joltage("123", 4),
+            Err("Bank '123' has fewer than 4 batteries".to_string())
+        );
+        let lines = vec!["12345".to_string(), "123".to_string()];
+        assert_eq!(
+            total_joltage(lines.clone(), 4),
+            Err("Bank '123' has fewer than 4 batteries".to_string())
+        );
+        assert!(
+            explain(&lines, 4).contains("    Highest: Bank '123' has fewer than 4 batteries\n")
+        );
+        assert!(highest_joltage("12a45", 2).is_err());
+    }
+
+    #[test]
+    fn test_total_joltage() {
+        let lines = vec!["987654321111111".to_string(), "811111111111119".to_string()];
+        assert_eq!(total_joltage(lines.clone(), 2), Ok(98 + 89));
+        assert_eq!(total_joltage(lines, 12), Ok(987654321111 + 811111111119));
+    }
+
+    #[test]
+    fn test_joltage_overflow() {
+        // 38 nines fit in a u128, 39 don't
+        let nines = "9".repeat(40);
+        assert_eq!(highest_joltage(&nines, 38), Ok(10u128.pow(38) - 1));
+        assert!(highest_joltage(&nines, 39).is_err());
+        assert_eq!(highest_joltage(&nines, 20), Ok(10u128.pow(20) - 1));
+
+        // u128::MAX is 340282366920938463463374607431768211455
+        let lines = vec!["1".to_string() + &"0".repeat(38); 2];
+        assert_eq!(total_joltage(lines, 39), Ok(2 * 10u128.pow(38)));
+        let lines = vec!["2".to_string() + &"0".repeat(38); 2];
+        assert_eq!(
+            total_joltage(lines, 39),
+            Err("Total joltage overflow at line 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_lowest_joltage() {
+        assert_eq!(select_batteries("92468", 2, false), Ok(vec![1, 2]));
+        assert_eq!(select_batteries("54321", 3, false), Ok(vec![2, 3, 4]));
+        assert_eq!(select_batteries("4321234", 3, false), Ok(vec![3, 4, 5]));
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_eq!(
+            highlight_bank("92468", &vec![0, 4]),
+            "\x1b[1;32m9\x1b[0m246\x1b[1;32m8\x1b[0m"
+        );
+        let explanation = explain(&vec!["92468".to_string()], 2);
+        assert_eq!(
+            explanation,
+            "Bank 1: 92468\n".to_string()
+                + "    Highest: 98 at [0, 4]\n"
+                + "        \x1b[1;32m9\x1b[0m246\x1b[1;32m8\x1b[0m\n"
+                + "    Lowest: 24 at [1, 2]\n"
+                + "        9\x1b[1;32m2\x1b[0m\x1b[1;32m4\x1b[0m68\n"
+        );
     }
 }
@@ -0,0 +1,128 @@
+use super::map_grid::Map;
+use crate::output;
+use std::fs;
+
+/**
+ * Round in which each location's roll was removed, or 0 if it was never
+ * removed (either because it stayed in place, or there was no roll there)
+ */
+pub fn removal_round_grid(map: &Map, rounds: &Vec<Vec<(usize, usize)>>) -> Vec<Vec<usize>> {
+    let mut grid = vec![vec![0; map.width()]; map.height()];
+    for (round, removed) in rounds.iter().enumerate() {
+        for (row, col) in removed {
+            grid[*row][*col] = round + 1;
+        }
+    }
+    return grid;
+}
+
+// Colour for a removal round, fading from yellow (first) through red to blue (last)
+fn heat_colour(round: usize, num_rounds: usize) -> (u8, u8, u8) {
+    let fraction = if num_rounds > 1 {
+        (round - 1) as f64 / (num_rounds - 1) as f64
+    } else {
+        0.0
+    };
+    if fraction < 0.5 {
+        let green = 255.0 * (1.0 - fraction * 2.0);
+        return (255, green as u8, 0);
+    }
+    let blue = 255.0 * (fraction - 0.5) * 2.0;
+    return ((255.0 - blue) as u8, 0, blue as u8);
+}
+
+/**
+ * Heatmap for the terminal: removed rolls are coloured by the round they
+ * were removed in, rolls left at the end are white, and empty locations are
+ * dots. The remaining map is the one left after all removals.
+ */
+pub fn render_ansi(remaining: &Map, round_grid: &Vec<Vec<usize>>) -> String {
+    let num_rounds = round_grid.iter().flatten().max().copied().unwrap_or(0);
+    let mut rendered = String::new();
+    for row in 0..remaining.height() {
+        for col in 0..remaining.width() {
+            let round = round_grid[row][col];
+            if round > 0 {
+                let (red, green, blue) = heat_colour(round, num_rounds);
+                rendered.push_str(&format!("\x1b[38;2;{red};{green};{blue}m@\x1b[0m"));
+            } else if remaining.get(row, col) {
+                rendered.push_str("\x1b[38;2;255;255;255m@\x1b[0m");
+            } else {
+                rendered.push('.');
+            }
+        }
+        rendered.push('\n');
+    }
+    return rendered;
+}
+
+// Heatmap as one pixel per location: empty is black, rolls left at the end are white
+pub fn pixels(remaining: &Map, round_grid: &Vec<Vec<usize>>) -> Vec<(u8, u8, u8)> {
+    let num_rounds = round_grid.iter().flatten().max().copied().unwrap_or(0);
+    let mut pixels = vec![];
+    for row in 0..remaining.height() {
+        for col in 0..remaining.width() {
+            let round = round_grid[row][col];
+            pixels.push(if round > 0 {
+                heat_colour(round, num_rounds)
+            } else if remaining.get(row, col) {
+                (255, 255, 255)
+            } else {
+                (0, 0, 0)
+            });
+        }
+    }
+    return pixels;
+}
+
+// Write the map before any removals, and after each round, as numbered text files
+pub fn write_frames(dir: &str, initial: &Map, rounds: &Vec<Vec<(usize, usize)>>) {
+    fs::create_dir_all(dir).expect("Failed to create frames directory");
+    let mut map = initial.clone();
+    let digits = rounds.len().to_string().len();
+    output::emit(&format!("{dir}/round_{:0digits$}.txt", 0), &map.render());
+    for (round, removed) in rounds.iter().enumerate() {
+        for (row, col) in removed {
+            map.set(*row, *col, false);
+        }
+        let path = format!("{dir}/round_{:0digits$}.txt", round + 1);
+        output::emit(&path, &map.render());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heatmap() {
+        let initial = Map::load(vec!["@@@".to_string(), "@..".to_string()]);
+        let mut remaining = initial.clone();
+        remaining.set(0, 0, false);
+        remaining.set(0, 1, false);
+        remaining.set(1, 0, false);
+        let rounds = vec![vec![(0, 0), (1, 0)], vec![(0, 1)]];
+        let round_grid = removal_round_grid(&initial, &rounds);
+        assert_eq!(round_grid, vec![vec![1, 2, 0], vec![1, 0, 0]]);
+
+        assert_eq!(heat_colour(1, 3), (255, 255, 0));
+        assert_eq!(heat_colour(3, 3), (0, 0, 255));
+        assert_eq!(
+            render_ansi(&remaining, &round_grid),
+            "\x1b[38;2;255;255;0m@\x1b[0m\x1b[38;2;0;0;255m@\x1b[0m".to_string()
+                + "\x1b[38;2;255;255;255m@\x1b[0m\n"
+                + "\x1b[38;2;255;255;0m@\x1b[0m..\n"
+        );
+        assert_eq!(
+            pixels(&remaining, &round_grid),
+            vec![
+                (255, 255, 0),
+                (0, 0, 255),
+                (255, 255, 255),
+                (255, 255, 0),
+                (0, 0, 0),
+                (0, 0, 0)
+            ]
+        );
+    }
+}
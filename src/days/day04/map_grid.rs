@@ -1,17 +1,163 @@
+// Offsets (row, col) of the cells counted as surrounding a cell
+pub struct Neighbourhood {
+    pub offsets: Vec<(isize, isize)>,
+    moore_radius: Option<usize>, // Set if the offsets make up a square around the cell
+}
+
+impl Neighbourhood {
+    // Cells within the given number of orthogonal steps (4 cells for radius 1)
+    pub fn von_neumann(radius: usize) -> Neighbourhood {
+        let r = radius as isize;
+        let mut offsets = vec![];
+        for row in -r..=r {
+            for col in -r..=r {
+                if (row, col) != (0, 0) && row.abs() + col.abs() <= r {
+                    offsets.push((row, col));
+                }
+            }
+        }
+        return Neighbourhood {
+            offsets,
+            moore_radius: None,
+        };
+    }
+
+    // Cells within the given number of king's moves (8 cells for radius 1)
+    pub fn moore(radius: usize) -> Neighbourhood {
+        let r = radius as isize;
+        let mut offsets = vec![];
+        for row in -r..=r {
+            for col in -r..=r {
+                if (row, col) != (0, 0) {
+                    offsets.push((row, col));
+                }
+            }
+        }
+        return Neighbourhood {
+            offsets,
+            moore_radius: Some(radius),
+        };
+    }
+
+    /**
+     * Parse a neighbourhood: "4" (von Neumann), "8" (Moore), or a kernel of
+     * row,col offsets separated by semicolons, e.g. "-1,0;1,0;0,-2;0,2".
+     * The radius applies to the 4 and 8 neighbourhoods only.
+     */
+    pub fn parse(spec: &str, radius: usize) -> Result<Neighbourhood, String> {
+        match spec {
+            "4" => return Ok(Neighbourhood::von_neumann(radius)),
+            "8" => return Ok(Neighbourhood::moore(radius)),
+            _ => (),
+        }
+        let mut offsets = vec![];
+        for pair in spec.split(";") {
+            let parts: Vec<&str> = pair.split(",").collect();
+            if parts.len() != 2 {
+                return Err(format!("Invalid kernel offset '{pair}', expected row,col"));
+            }
+            let row: isize = parts[0]
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid row offset in '{pair}'"))?;
+            let col: isize = parts[1]
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid col offset in '{pair}'"))?;
+            if (row, col) == (0, 0) {
+                return Err("Kernel can't include the cell itself (0,0)".to_string());
+            }
+            if !offsets.contains(&(row, col)) {
+                offsets.push((row, col));
+            }
+        }
+        return Ok(Neighbourhood {
+            offsets,
+            moore_radius: None,
+        });
+    }
+}
+
+// How locations beyond the edges of the map are treated
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Boundary {
+    Empty,  // Nothing beyond the edges
+    Filled, // Walls beyond the edges, which count as rolls
+    Wrap,   // The map repeats, so the edges wrap around
+}
+
+impl Boundary {
+    pub fn parse(mode: &str) -> Result<Boundary, String> {
+        return match mode {
+            "empty" => Ok(Boundary::Empty),
+            "filled" => Ok(Boundary::Filled),
+            "wrap" => Ok(Boundary::Wrap),
+            _ => Err(format!(
+                "Unknown boundary mode '{mode}', expected empty, filled or wrap"
+            )),
+        };
+    }
+}
+
+/**
+ * Grid of locations which may have a roll of paper, packed one bit per
+ * location into a flat list of words. Each row starts on a new word, so a
+ * run of columns can be read from at most two words.
+ */
+#[derive(Clone)]
 pub struct Map {
-    pub grid: Vec<Vec<bool>>, // True if grid location has a roll of paper
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    bits: Vec<u64>, // Bit set if grid location has a roll of paper
+    pub boundary: Boundary,
 }
 
+const WORD_BITS: usize = 64;
+
 impl Map {
     pub fn new() -> Map {
-        Map { grid: vec![] }
+        Map {
+            width: 0,
+            height: 0,
+            words_per_row: 0,
+            bits: vec![],
+            boundary: Boundary::Empty,
+        }
     }
 
+    // Load a map whose rows must all be the same length
+    #[allow(dead_code)]
     pub fn load(lines: Vec<String>) -> Map {
+        return Map::try_load(lines, false).unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    /**
+     * Load a map, checking that every row is the same length as the first.
+     * If pad is set, short rows are filled with empty locations instead, up
+     * to the length of the longest row. Blank lines at the end are ignored.
+     */
+    pub fn try_load(mut lines: Vec<String>, pad: bool) -> Result<Map, String> {
+        while lines.last().is_some_and(|line| line == "") {
+            lines.pop();
+        }
+        let width = if pad {
+            lines.iter().map(|line| line.len()).max().unwrap_or(0)
+        } else {
+            lines.first().map(|line| line.len()).unwrap_or(0)
+        };
         let mut map = Map::new();
+        map.resize(lines.len(), width);
         let mut row: usize = 0;
         let mut col: usize;
         for line in lines {
+            if !pad && line.len() != width {
+                return Err(format!(
+                    "Row {} has {} columns, expected {width} (use --pad to pad short rows)",
+                    row + 1,
+                    line.len()
+                ));
+            }
             col = 0;
             for ch in line.bytes() {
                 match ch {
@@ -22,25 +168,61 @@ impl Map {
             }
             row += 1;
         }
-        return map;
+        return Ok(map);
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    // Grow the map to the given size, repacking rows if they need more words
+    fn resize(&mut self, height: usize, width: usize) {
+        let words_per_row = width.div_ceil(WORD_BITS);
+        if words_per_row > self.words_per_row {
+            let mut bits = vec![0; height * words_per_row];
+            for row in 0..self.height {
+                let old_start = row * self.words_per_row;
+                let new_start = row * words_per_row;
+                bits[new_start..new_start + self.words_per_row]
+                    .copy_from_slice(&self.bits[old_start..old_start + self.words_per_row]);
+            }
+            self.bits = bits;
+            self.words_per_row = words_per_row;
+        } else {
+            self.bits.resize(height * self.words_per_row, 0);
+        }
+        self.height = height;
+        self.width = width;
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        let word = self.bits[row * self.words_per_row + col / WORD_BITS];
+        return word >> (col % WORD_BITS) & 1 == 1;
     }
 
+    // Set a location, growing the map as needed to include it
     pub fn set(&mut self, row: usize, col: usize, val: bool) {
-        if self.grid.len() <= row {
-            self.grid.push(vec![]);
+        if row >= self.height || col >= self.width {
+            self.resize(self.height.max(row + 1), self.width.max(col + 1));
         }
-        if self.grid[row].len() <= col {
-            self.grid[row].push(false);
+        let word = &mut self.bits[row * self.words_per_row + col / WORD_BITS];
+        if val {
+            *word |= 1 << (col % WORD_BITS);
+        } else {
+            *word &= !(1 << (col % WORD_BITS));
         }
-        self.grid[row][col] = val;
     }
 
     #[allow(dead_code)]
     pub fn render(&self) -> String {
         let mut chars = vec![];
-        for row in &self.grid {
-            for col in row {
-                chars.push(match col {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                chars.push(match self.get(row, col) {
                     true => b'@',
                     false => b'.',
                 });
@@ -50,50 +232,111 @@ impl Map {
         return String::from_utf8(chars).unwrap();
     }
 
-    pub fn surrounding_rolls(&self, row: usize, col: usize) -> u8 {
-        let mut rolls = 0;
+    // Position offset from the given one, if it's on the map (always, if it wraps)
+    pub fn offset(&self, row: usize, col: usize, offset: (isize, isize)) -> Option<(usize, usize)> {
+        if self.boundary == Boundary::Wrap {
+            let new_row = (row as isize + offset.0).rem_euclid(self.height as isize);
+            let new_col = (col as isize + offset.1).rem_euclid(self.width as isize);
+            return Some((new_row as usize, new_col as usize));
+        }
+        let new_row = row.checked_add_signed(offset.0)?;
+        let new_col = col.checked_add_signed(offset.1)?;
+        if new_row >= self.height || new_col >= self.width {
+            return None;
+        }
+        return Some((new_row, new_col));
+    }
 
-        let left_ok = col > 0;
-        let right_ok = col < self.grid[0].len() - 1;
-        let up_ok = row > 0;
-        let down_ok = row < self.grid.len() - 1;
+    // Bits for len (up to 64) columns of a row, starting at col, which must all be on the map
+    fn row_bits(&self, row: usize, col: usize, len: usize) -> u64 {
+        if len == 0 {
+            return 0;
+        }
+        let word_idx = row * self.words_per_row + col / WORD_BITS;
+        let shift = col % WORD_BITS;
+        let mut bits = self.bits[word_idx] >> shift;
+        if shift + len > WORD_BITS {
+            bits |= self.bits[word_idx + 1] << (WORD_BITS - shift);
+        }
+        if len < WORD_BITS {
+            bits &= (1 << len) - 1;
+        }
+        return bits;
+    }
 
-        // above
-        if up_ok {
-            if left_ok && self.grid[row - 1][col - 1] {
-                rolls += 1;
-            }
-            if self.grid[row - 1][col] {
-                rolls += 1;
-            }
-            if right_ok && self.grid[row - 1][col + 1] {
-                rolls += 1;
+    // Number of rolls in len (up to 64) columns of a row, starting at col, which may be off the map
+    fn rolls_in_window(&self, row: isize, col: isize, len: usize) -> usize {
+        if self.boundary == Boundary::Wrap {
+            let row = row.rem_euclid(self.height as isize) as usize;
+            let mut rolls = 0;
+            for offset in 0..len as isize {
+                let col = (col + offset).rem_euclid(self.width as isize) as usize;
+                if self.get(row, col) {
+                    rolls += 1;
+                }
             }
+            return rolls;
         }
 
-        // same row
-        if left_ok && self.grid[row][col - 1] {
-            rolls += 1;
+        let walls = self.boundary == Boundary::Filled;
+        if row < 0 || row >= self.height as isize {
+            return if walls { len } else { 0 };
+        }
+        let start = col.max(0);
+        let end = (col + len as isize).min(self.width as isize);
+        let on_map = (end - start).max(0) as usize;
+        let mut rolls = 0;
+        if on_map > 0 {
+            rolls += self
+                .row_bits(row as usize, start as usize, on_map)
+                .count_ones() as usize;
         }
-        if right_ok && self.grid[row][col + 1] {
-            rolls += 1;
+        if walls {
+            rolls += len - on_map;
         }
+        return rolls;
+    }
 
-        // below
-        if down_ok {
-            if left_ok && self.grid[row + 1][col - 1] {
-                rolls += 1;
-            }
-            if self.grid[row + 1][col] {
-                rolls += 1;
+    // Number of rolls in the given neighbourhood of a location
+    pub fn neighbourhood_rolls(
+        &self,
+        row: usize,
+        col: usize,
+        neighbourhood: &Neighbourhood,
+    ) -> usize {
+        // Square neighbourhoods are counted a row of bits at a time
+        if let Some(radius) = neighbourhood.moore_radius {
+            let len = radius * 2 + 1;
+            if len <= WORD_BITS {
+                let r = radius as isize;
+                let mut rolls = 0;
+                for row_offset in -r..=r {
+                    rolls += self.rolls_in_window(row as isize + row_offset, col as isize - r, len);
+                }
+                if self.get(row, col) {
+                    rolls -= 1;
+                }
+                return rolls;
             }
-            if right_ok && self.grid[row + 1][col + 1] {
+        }
+
+        let mut rolls = 0;
+        for offset in &neighbourhood.offsets {
+            let is_roll = match self.offset(row, col, *offset) {
+                Some((n_row, n_col)) => self.get(n_row, n_col),
+                None => self.boundary == Boundary::Filled,
+            };
+            if is_roll {
                 rolls += 1;
             }
         }
-
         return rolls;
     }
+
+    #[allow(dead_code)]
+    pub fn surrounding_rolls(&self, row: usize, col: usize) -> u8 {
+        return self.neighbourhood_rolls(row, col, &Neighbourhood::moore(1)) as u8;
+    }
 }
 
 #[cfg(test)]
@@ -120,4 +363,145 @@ mod tests {
         assert_eq!(map.surrounding_rolls(2, 1), 5);
         assert_eq!(map.surrounding_rolls(2, 2), 3);
     }
+
+    #[test]
+    fn test_neighbourhood() {
+        assert_eq!(Neighbourhood::von_neumann(1).offsets.len(), 4);
+        assert_eq!(Neighbourhood::von_neumann(2).offsets.len(), 12);
+        assert_eq!(Neighbourhood::moore(1).offsets.len(), 8);
+        assert_eq!(Neighbourhood::moore(2).offsets.len(), 24);
+        assert_eq!(
+            Neighbourhood::parse("-1,0; 1,0", 1).unwrap().offsets,
+            vec![(-1, 0), (1, 0)]
+        );
+        assert!(Neighbourhood::parse("0,0", 1).is_err());
+        assert!(Neighbourhood::parse("1", 1).is_err());
+
+        let map = Map::load(vec![
+            "@@@".to_string(),
+            "@@@".to_string(),
+            "@@@".to_string(),
+        ]);
+        let moore = Neighbourhood::moore(1);
+        let von_neumann = Neighbourhood::von_neumann(1);
+        for row in 0..3 {
+            for col in 0..3 {
+                assert_eq!(
+                    map.neighbourhood_rolls(row, col, &moore),
+                    map.surrounding_rolls(row, col) as usize
+                );
+            }
+        }
+        assert_eq!(map.neighbourhood_rolls(0, 0, &von_neumann), 2);
+        assert_eq!(map.neighbourhood_rolls(1, 1, &von_neumann), 4);
+        assert_eq!(map.neighbourhood_rolls(1, 1, &Neighbourhood::moore(2)), 8);
+    }
+
+    #[test]
+    fn test_boundary() {
+        let mut map = Map::load(vec![
+            "@..".to_string(),
+            "...".to_string(),
+            "..@".to_string(),
+        ]);
+        assert_eq!(map.surrounding_rolls(0, 0), 0);
+        assert_eq!(map.surrounding_rolls(1, 1), 2);
+
+        map.boundary = Boundary::Filled;
+        assert_eq!(map.surrounding_rolls(0, 0), 5);
+        assert_eq!(map.surrounding_rolls(0, 1), 4);
+        assert_eq!(map.surrounding_rolls(1, 1), 2);
+        assert_eq!(map.surrounding_rolls(2, 2), 5);
+
+        map.boundary = Boundary::Wrap;
+        assert_eq!(map.surrounding_rolls(0, 0), 1);
+        assert_eq!(map.surrounding_rolls(0, 2), 2);
+        assert_eq!(map.surrounding_rolls(1, 0), 2);
+        assert_eq!(map.offset(0, 0, (-1, -1)), Some((2, 2)));
+
+        assert_eq!(Boundary::parse("wrap"), Ok(Boundary::Wrap));
+        assert!(Boundary::parse("solid").is_err());
+    }
+
+    #[test]
+    fn test_bit_packing() {
+        // Rows longer than a word, with a pattern that doesn't line up with word boundaries
+        let mut lines = vec![];
+        for row in 0..5 {
+            let line: String = (0..150)
+                .map(|col| {
+                    if (row * 7 + col * 3) % 5 < 2 {
+                        '@'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            lines.push(line);
+        }
+        let mut map = Map::load(lines.clone());
+        assert_eq!(map.render(), lines.join("\n") + "\n");
+
+        for boundary in [Boundary::Empty, Boundary::Filled, Boundary::Wrap] {
+            map.boundary = boundary;
+            for radius in [1, 2] {
+                let square = Neighbourhood::moore(radius);
+                let kernel = Neighbourhood {
+                    offsets: square.offsets.clone(),
+                    moore_radius: None,
+                };
+                for row in 0..5 {
+                    for col in 0..150 {
+                        assert_eq!(
+                            map.neighbourhood_rolls(row, col, &square),
+                            map.neighbourhood_rolls(row, col, &kernel),
+                            "{:?} radius {radius} at ({row}, {col})",
+                            boundary
+                        );
+                    }
+                }
+            }
+        }
+
+        // Growing past a word boundary keeps existing rolls
+        let mut map = Map::load(vec!["@.@".to_string(), ".@.".to_string()]);
+        map.set(1, 70, true);
+        assert_eq!(map.width(), 71);
+        assert!(map.get(0, 0) && !map.get(0, 1) && map.get(0, 2));
+        assert!(map.get(1, 1) && map.get(1, 70) && !map.get(1, 69));
+    }
+
+    #[test]
+    fn test_ragged() {
+        let lines = vec!["@@@".to_string(), "@".to_string(), "@@".to_string()];
+        assert_eq!(
+            Map::try_load(lines.clone(), false).err(),
+            Some("Row 2 has 1 columns, expected 3 (use --pad to pad short rows)".to_string())
+        );
+        let map = Map::try_load(lines, true).unwrap();
+        assert_eq!(map.render(), "@@@\n@..\n@@.\n");
+
+        // Longer rows are padded up to, or rejected against, the first row
+        let lines = vec!["@".to_string(), "@@".to_string(), "".to_string()];
+        assert!(Map::try_load(lines.clone(), false).is_err());
+        assert_eq!(Map::try_load(lines, true).unwrap().render(), "@.\n@@\n");
+
+        // Trailing blank lines are ignored
+        let lines = vec!["@.".to_string(), ".@".to_string(), "".to_string()];
+        assert_eq!(Map::try_load(lines, false).unwrap().height(), 2);
+    }
+
+    #[test]
+    fn test_sparse() {
+        let mut map = Map::new();
+        map.set(5, 5, true);
+        assert_eq!((map.height(), map.width()), (6, 6));
+        assert!(map.get(5, 5));
+        map.set(2, 100, true);
+        assert_eq!((map.height(), map.width()), (6, 101));
+        assert!(map.get(5, 5) && map.get(2, 100));
+        assert_eq!(map.surrounding_rolls(4, 4), 1);
+        map.set(1, 1, false);
+        assert_eq!(map.render().matches('@').count(), 2);
+    }
 }
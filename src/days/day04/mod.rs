@@ -1,15 +1,34 @@
+mod heatmap;
 mod map_grid;
 
-use map_grid::Map;
+use crate::args;
+use crate::output;
+use map_grid::{Boundary, Map, Neighbourhood};
 
-pub fn find_accessible(map: &Map) -> Vec<(usize, usize)> {
+// A roll is accessible if fewer than threshold rolls are in its neighbourhood
+pub struct AccessRules {
+    pub neighbourhood: Neighbourhood,
+    pub threshold: usize,
+}
+
+impl AccessRules {
+    // Fewer than 4 of the 8 surrounding cells have rolls
+    pub fn standard() -> AccessRules {
+        AccessRules {
+            neighbourhood: Neighbourhood::moore(1),
+            threshold: 4,
+        }
+    }
+}
+
+pub fn find_accessible(map: &Map, rules: &AccessRules) -> Vec<(usize, usize)> {
     let mut accessible = vec![];
-    for row in 0..map.grid.len() {
-        for col in 0..map.grid[0].len() {
-            if map.grid[row][col] == false {
+    for row in 0..map.height() {
+        for col in 0..map.width() {
+            if map.get(row, col) == false {
                 continue;
             }
-            if map.surrounding_rolls(row, col) < 4 {
+            if map.neighbourhood_rolls(row, col, &rules.neighbourhood) < rules.threshold {
                 accessible.push((row, col));
             }
         }
@@ -17,29 +36,135 @@ pub fn find_accessible(map: &Map) -> Vec<(usize, usize)> {
     return accessible;
 }
 
+/**
+ * Options:
+ *   --neighbourhood=N  4, 8 (default), or a kernel of row,col offsets, e.g. "-1,0;1,0"
+ *   --radius=N         Radius of the 4 or 8 neighbourhood (default 1)
+ *   --threshold=N      Rolls are accessible with fewer than N neighbouring rolls (default 4)
+ *   --boundary=MODE    Beyond the edges is empty (default), filled with walls, or wraps around
+ *   --pad              Pad short rows with empty locations, rather than rejecting them
+ *   --heatmap[=FILE]   Part 2: show the round each roll was removed in, or write it to FILE as a PPM image
+ *   --frames=DIR       Part 2: write the map after each round to numbered files in DIR
+ */
+fn get_access_rules() -> AccessRules {
+    let mut rules = AccessRules::standard();
+    let radius: usize = match args::get_option("radius") {
+        Some(value) => value.parse().expect("Failed to parse radius"),
+        None => 1,
+    };
+    rules.neighbourhood = match args::get_option("neighbourhood") {
+        Some(spec) => Neighbourhood::parse(&spec, radius).expect("Invalid neighbourhood"),
+        None => Neighbourhood::moore(radius),
+    };
+    if let Some(value) = args::get_option("threshold") {
+        rules.threshold = value.parse().expect("Failed to parse threshold");
+    }
+    return rules;
+}
+
+fn load_map(lines: Vec<String>) -> Map {
+    let pad = args::get_option("pad").is_some();
+    let mut map = Map::try_load(lines, pad).unwrap_or_else(|err| panic!("Invalid map: {err}"));
+    if let Some(mode) = args::get_option("boundary") {
+        map.boundary = Boundary::parse(&mode).expect("Invalid boundary");
+    }
+    return map;
+}
+
 pub fn part1(lines: Vec<String>) {
-    let map = Map::load(lines);
-    let accessible = find_accessible(&map);
+    let map = load_map(lines);
+    let accessible = find_accessible(&map, &get_access_rules());
     println!("Accessible: {}: {:?}", accessible.len(), accessible);
 }
 
-pub fn part2(lines: Vec<String>) {
-    let mut map = Map::load(lines);
-    let mut round = 0;
-    let mut total_removed = 0;
+/**
+ * Repeatedly remove all accessible rolls, returning the rolls removed in each round.
+ *
+ * Keeps a count of neighbouring rolls for every cell, and only re-checks the
+ * cells whose neighbourhoods contain rolls removed in the previous round, as
+ * nothing else can have become accessible.
+ */
+fn removal_rounds(map: &mut Map, rules: &AccessRules) -> Vec<Vec<(usize, usize)>> {
+    let height = map.height();
+    let width = map.width();
+    let mut counts = vec![vec![0usize; width]; height];
+    let mut candidates = vec![];
+    for row in 0..height {
+        for col in 0..width {
+            counts[row][col] = map.neighbourhood_rolls(row, col, &rules.neighbourhood);
+            if map.get(row, col) {
+                candidates.push((row, col));
+            }
+        }
+    }
+
+    // Cells which count a given cell as a neighbour (the kernel may not be symmetric)
+    let reverse_offsets: Vec<(isize, isize)> = rules
+        .neighbourhood
+        .offsets
+        .iter()
+        .map(|(row, col)| (-row, -col))
+        .collect();
+
+    // Round in which each cell was last queued, to avoid checking it twice
+    let mut queued = vec![vec![0usize; width]; height];
+    let mut rounds = vec![];
     loop {
-        round += 1;
-        let accessible = find_accessible(&map);
-        println!("Round {round}: removing {} rolls", accessible.len());
+        let round = rounds.len() + 1;
+        let mut accessible = vec![];
+        for (row, col) in candidates {
+            if map.get(row, col) && counts[row][col] < rules.threshold {
+                accessible.push((row, col));
+            }
+        }
         if accessible.len() == 0 {
             break;
         }
-        total_removed += accessible.len();
-        for rem in accessible {
-            map.set(rem.0, rem.1, false);
+        for (row, col) in &accessible {
+            map.set(*row, *col, false);
+        }
+        candidates = vec![];
+        for (row, col) in &accessible {
+            for offset in &reverse_offsets {
+                let Some((n_row, n_col)) = map.offset(*row, *col, *offset) else {
+                    continue;
+                };
+                counts[n_row][n_col] -= 1;
+                if map.get(n_row, n_col) && queued[n_row][n_col] != round {
+                    queued[n_row][n_col] = round;
+                    candidates.push((n_row, n_col));
+                }
+            }
         }
+        rounds.push(accessible);
     }
+    return rounds;
+}
+
+pub fn part2(lines: Vec<String>) {
+    let initial = load_map(lines);
+    let mut map = initial.clone();
+    let rounds = removal_rounds(&mut map, &get_access_rules());
+    let mut total_removed = 0;
+    for (round, removed) in rounds.iter().enumerate() {
+        println!("Round {}: removing {} rolls", round + 1, removed.len());
+        total_removed += removed.len();
+    }
+    println!("Round {}: removing 0 rolls", rounds.len() + 1);
     println!("Total removed: {total_removed}");
+
+    if let Some(path) = args::get_option("heatmap") {
+        let round_grid = heatmap::removal_round_grid(&map, &rounds);
+        if output::is_stdout(&path) {
+            print!("{}", heatmap::render_ansi(&map, &round_grid));
+        } else {
+            let pixels = heatmap::pixels(&map, &round_grid);
+            output::write_ppm(&path, map.width(), map.height(), &pixels);
+        }
+    }
+    if let Some(dir) = args::get_option("frames") {
+        heatmap::write_frames(&dir, &initial, &rounds);
+    }
 }
 
 #[cfg(test)]
@@ -49,22 +174,140 @@ mod tests {
     #[test]
     fn test_count_accessible() {
         assert_eq!(
-            find_accessible(&Map::load(vec![
-                "@@@".to_string(),
-                "@@@".to_string(),
-                "@@@".to_string(),
-            ]))
+            find_accessible(
+                &Map::load(vec![
+                    "@@@".to_string(),
+                    "@@@".to_string(),
+                    "@@@".to_string(),
+                ]),
+                &AccessRules::standard()
+            )
             .len(),
             4
         );
         assert_eq!(
-            find_accessible(&Map::load(vec![
-                ".@.".to_string(),
-                "@@@".to_string(),
-                ".@.".to_string(),
-            ]))
+            find_accessible(
+                &Map::load(vec![
+                    ".@.".to_string(),
+                    "@@@".to_string(),
+                    ".@.".to_string(),
+                ]),
+                &AccessRules::standard()
+            )
             .len(),
             4
         );
     }
+
+    fn round_sizes(rounds: Vec<Vec<(usize, usize)>>) -> Vec<usize> {
+        return rounds.iter().map(|removed| removed.len()).collect();
+    }
+
+    // Straightforward simulation, checking the whole map every round
+    fn removal_rounds_full_scan(map: &mut Map, rules: &AccessRules) -> Vec<usize> {
+        let mut rounds = vec![];
+        loop {
+            let accessible = find_accessible(map, rules);
+            if accessible.len() == 0 {
+                break;
+            }
+            rounds.push(accessible.len());
+            for rem in accessible {
+                map.set(rem.0, rem.1, false);
+            }
+        }
+        return rounds;
+    }
+
+    fn example() -> Vec<String> {
+        return [
+            "..@@.@@@@.",
+            "@@@.@.@.@@",
+            "@@@@@.@.@@",
+            "@.@@@@..@.",
+            "@@.@@@@.@@",
+            ".@@@@@@@.@",
+            ".@.@.@.@@@",
+            "@.@@@.@@@@",
+            ".@@@@@@@@.",
+            "@.@.@@@.@.",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    }
+
+    #[test]
+    fn test_removal_rounds() {
+        let rules = AccessRules::standard();
+        let rounds = round_sizes(removal_rounds(&mut Map::load(example()), &rules));
+        assert_eq!(rounds.iter().sum::<usize>(), 43);
+        assert_eq!(
+            rounds,
+            removal_rounds_full_scan(&mut Map::load(example()), &rules)
+        );
+
+        let solid = vec!["@@@@@@".to_string(); 6];
+        assert_eq!(
+            round_sizes(removal_rounds(&mut Map::load(solid.clone()), &rules)),
+            removal_rounds_full_scan(&mut Map::load(solid), &rules)
+        );
+    }
+
+    #[test]
+    fn test_access_rules() {
+        let plus = vec![".@.".to_string(), "@@@".to_string(), ".@.".to_string()];
+        let von_neumann = AccessRules {
+            neighbourhood: Neighbourhood::von_neumann(1),
+            threshold: 2,
+        };
+        assert_eq!(
+            find_accessible(&Map::load(plus.clone()), &von_neumann).len(),
+            4
+        );
+        let strict = AccessRules {
+            neighbourhood: Neighbourhood::moore(1),
+            threshold: 1,
+        };
+        assert_eq!(find_accessible(&Map::load(plus), &strict).len(), 0);
+
+        let all_rules = vec![
+            von_neumann,
+            AccessRules {
+                neighbourhood: Neighbourhood::moore(2),
+                threshold: 10,
+            },
+            // Only looks right and down, so isn't symmetric
+            AccessRules {
+                neighbourhood: Neighbourhood::parse("0,1;1,0;1,1", 1).unwrap(),
+                threshold: 2,
+            },
+        ];
+        for rules in &all_rules {
+            assert_eq!(
+                round_sizes(removal_rounds(&mut Map::load(example()), rules)),
+                removal_rounds_full_scan(&mut Map::load(example()), rules)
+            );
+        }
+    }
+
+    #[test]
+    fn test_boundary_removal() {
+        let rules = AccessRules::standard();
+        for boundary in [Boundary::Empty, Boundary::Filled, Boundary::Wrap] {
+            let mut map = Map::load(example());
+            map.boundary = boundary;
+            let mut full_scan_map = Map::load(example());
+            full_scan_map.boundary = boundary;
+            assert_eq!(
+                round_sizes(removal_rounds(&mut map, &rules)),
+                removal_rounds_full_scan(&mut full_scan_map, &rules)
+            );
+        }
+
+        // Walls keep the corners of a solid block in place
+        let mut map = Map::load(vec!["@@@".to_string(); 3]);
+        map.boundary = Boundary::Filled;
+        assert_eq!(find_accessible(&map, &rules).len(), 0);
+    }
 }
@@ -1,140 +1,237 @@
+use crate::args;
+use crate::lines;
+use std::fs;
+
 const DEBUG_OUTPUT: bool = true;
 
-fn is_fresh(ingredient: u64, fresh_ingredients: &Vec<(u64, u64)>) -> bool {
+// Inclusive range of ingredient IDs
+type Range = (u64, u64);
+
+// Check an ID against merged (sorted, disjoint) ranges, by binary search
+fn in_ranges(id: u64, ranges: &Vec<Range>) -> bool {
+    // First range which doesn't end before the ID
+    let idx = ranges.partition_point(|range| range.1 < id);
+    return idx < ranges.len() && ranges[idx].0 <= id;
+}
+
+fn is_fresh(ingredient: u64, fresh_ranges: &Vec<Range>) -> bool {
     if DEBUG_OUTPUT {
         print!("Ingredient {ingredient}");
     }
-    let found = false;
-    for fresh_range in fresh_ingredients {
-        if ingredient >= fresh_range.0 && ingredient <= fresh_range.1 {
-            if DEBUG_OUTPUT && !found {
-                println!(": fresh");
-            }
-            return true;
-        }
+    let fresh = in_ranges(ingredient, fresh_ranges);
+    if DEBUG_OUTPUT {
+        println!(": {}", if fresh { "fresh" } else { "stale" });
     }
-    if DEBUG_OUTPUT && !found {
-        println!(": stale");
+    return fresh;
+}
+
+// Parse a range line such as "3-5", which must not end before it starts
+fn parse_range(line: &str) -> Result<Range, String> {
+    let (el1, el2) = line
+        .split_once("-")
+        .ok_or(format!("Range '{line}' must be START-END"))?;
+    let range_start: u64 = el1
+        .parse()
+        .map_err(|_| format!("Range '{line}' has an invalid start"))?;
+    let range_end: u64 = el2
+        .parse()
+        .map_err(|_| format!("Range '{line}' has an invalid end"))?;
+    if range_start > range_end {
+        return Err(format!("Range '{line}' starts after it ends"));
     }
-    return false;
+    return Ok((range_start, range_end));
 }
 
-pub fn part1(lines: Vec<String>) {
-    let mut fresh_ingredients: Vec<(u64, u64)> = vec![];
-    let mut num_fresh = 0;
-    for line in lines {
+/**
+ * Load all ranges and ingredients, wherever they appear in the input, so
+ * that an ingredient listed before its range is still found to be fresh.
+ */
+fn load_inventory(lines: Vec<String>) -> Result<(Vec<Range>, Vec<u64>), String> {
+    let mut fresh_ranges: Vec<Range> = vec![];
+    let mut ingredients = vec![];
+    for (line_num, line) in lines.iter().enumerate() {
         if line == "" {
             continue;
         }
-        let mut parts = line.split("-");
-        let el1 = parts.next().unwrap();
-        let el2 = parts.next();
-
-        match el2 {
-            Some(el2_value) => {
-                let range_start = el1.parse().expect("Must be a number");
-                let range_end = el2_value.parse().expect("Must be a number");
-                if DEBUG_OUTPUT {
-                    println!("Fresh range: {range_start}-{range_end}")
-                }
-                fresh_ingredients.push((range_start, range_end));
-            }
-            None => {
-                let ingredient: u64 = el1.parse().expect("Must be a number");
-                if is_fresh(ingredient, &fresh_ingredients) {
-                    num_fresh += 1;
-                }
+        if line.contains("-") {
+            let range = parse_range(line).map_err(|err| format!("Line {}: {err}", line_num + 1))?;
+            if DEBUG_OUTPUT {
+                println!("Fresh range: {}-{}", range.0, range.1)
             }
+            fresh_ranges.push(range);
+        } else {
+            let ingredient: u64 = line
+                .parse()
+                .map_err(|_| format!("Line {}: Invalid ingredient '{line}'", line_num + 1))?;
+            ingredients.push(ingredient);
+        }
+    }
+    return Ok((fresh_ranges, ingredients));
+}
+
+fn count_fresh(lines: Vec<String>) -> usize {
+    let (fresh_ranges, ingredients) = load_inventory(lines).unwrap_or_else(|err| panic!("{err}"));
+    let fresh_ranges = merge_ranges(fresh_ranges);
+    let mut num_fresh = 0;
+    for ingredient in ingredients {
+        if is_fresh(ingredient, &fresh_ranges) {
+            num_fresh += 1;
         }
     }
-    println!("Fresh ingredients: {num_fresh}");
+    return num_fresh;
 }
 
-fn load_ranges(lines: Vec<String>) -> Vec<(u64, u64)> {
-    let mut fresh_ranges: Vec<(u64, u64)> = vec![];
-    for line in lines {
+pub fn part1(lines: Vec<String>) {
+    println!("Fresh ingredients: {}", count_fresh(lines));
+}
+
+// Load ranges up to the first blank line, naming the line of the first invalid range
+fn load_ranges(lines: Vec<String>) -> Result<Vec<Range>, String> {
+    let mut fresh_ranges: Vec<Range> = vec![];
+    for (line_num, line) in lines.iter().enumerate() {
         if line == "" {
             break;
         }
-        let mut parts = line.split("-");
-        let el1 = parts.next().unwrap();
-        let el2 = parts.next();
-        match el2 {
-            None => continue,
-            Some(value) => {
-                let range_start: u64 = el1.parse().expect("Must be a number");
-                let range_end: u64 = value.parse().expect("Must be a number");
-                fresh_ranges.push((range_start, range_end));
-            }
+        if !line.contains("-") {
+            continue;
         }
+        let range = parse_range(line).map_err(|err| format!("Line {}: {err}", line_num + 1))?;
+        fresh_ranges.push(range);
     }
-    return fresh_ranges;
+    return Ok(fresh_ranges);
 }
 
-fn ranges_overlap(range1: &(u64, u64), range2: &(u64, u64)) -> (bool, u64, u64) {
-    let mut updated = false;
-    let mut new_start = range1.0;
-    let mut new_end = range1.1;
+/**
+ * Check whether two ranges overlap or are contiguous, in either order.
+ * Returns whether they do, and the merged range if so (or range1 if not).
+ */
+fn ranges_overlap(range1: &Range, range2: &Range) -> (bool, u64, u64) {
+    let (first, second) = if range1.0 <= range2.0 {
+        (range1, range2)
+    } else {
+        (range2, range1)
+    };
 
-    // contiguous ranges
-    if range1.1 + 1 == range2.0 {
-        return (true, range1.0, range2.1);
+    // second starts after the end of first, with a gap (checked without adding, to avoid overflow)
+    if second.0 > first.1 && second.0 - first.1 > 1 {
+        return (false, range1.0, range1.1);
     }
+    return (true, first.0, first.1.max(second.1));
+}
 
-    // range2 overlaps range1 start
-    if range2.0 <= range1.0 && range2.1 >= range1.0 {
-        new_start = range2.0;
-        updated = true;
+/**
+ * Merge overlapping or contiguous ranges, by sorting them by start and then
+ * sweeping through, extending the last merged range while the next one
+ * starts inside it or immediately after it.
+ */
+fn merge_ranges(mut ranges: Vec<Range>) -> Vec<Range> {
+    ranges.sort();
+    let mut merged: Vec<Range> = vec![];
+    for range in ranges {
+        if let Some(last) = merged.last_mut() {
+            let (overlap, new_start, new_end) = ranges_overlap(last, &range);
+            if overlap {
+                *last = (new_start, new_end);
+                continue;
+            }
+        }
+        merged.push(range);
     }
+    return merged;
+}
 
-    // range2 overlaps range1 end (can coincide with overlapping start)
-    if range2.1 >= range1.1 && range2.0 <= range1.1 {
-        new_end = range2.1;
-        updated = true;
+// Number of IDs in a list of disjoint ranges; can be up to 2^64, so needs a u128
+fn count_ids(ranges: &Vec<Range>) -> u128 {
+    let mut total_ids = 0;
+    for range in ranges {
+        total_ids += u128::from(range.1 - range.0) + 1;
     }
-    return (updated, new_start, new_end);
+    return total_ids;
 }
 
 pub fn part2(lines: Vec<String>) {
-    let mut fresh_ranges: Vec<(u64, u64)> = load_ranges(lines);
+    let fresh_ranges = load_ranges(lines).unwrap_or_else(|err| panic!("{err}"));
     println!("Fresh ranges: {:?}", fresh_ranges);
-    loop {
-        let mut merged = vec![];
-        for i in 0..fresh_ranges.len() {
-            for j in 0..fresh_ranges.len() {
-                if i == j {
-                    continue;
-                }
-                let (overlap, new_start, new_end) =
-                    ranges_overlap(&fresh_ranges[i], &fresh_ranges[j]);
-                if overlap {
-                    if DEBUG_OUTPUT {
-                        println!("Merge {:?} and {:?}", fresh_ranges[i], fresh_ranges[j])
-                    }
-                    fresh_ranges[i].0 = new_start;
-                    fresh_ranges[i].1 = new_end;
-                    merged.push(j);
-                }
-            }
-            if merged.len() > 0 {
-                break;
-            }
-        }
-        if merged.len() > 0 {
-            merged.reverse();
-            for i in merged {
-                fresh_ranges.remove(i);
-            }
-        } else {
+    let fresh_ranges = merge_ranges(fresh_ranges);
+    println!("Fresh ingredient ranges: {:?}", fresh_ranges);
+    println!("Fresh ingredient IDs: {:?}", count_ids(&fresh_ranges));
+}
+
+// Number of IDs from start to end (inclusive) in merged ranges
+fn count_ids_between(start: u64, end: u64, ranges: &Vec<Range>) -> u128 {
+    let mut total_ids = 0;
+    let first = ranges.partition_point(|range| range.1 < start);
+    for range in &ranges[first..] {
+        if range.0 > end {
             break;
         }
+        total_ids += u128::from(range.1.min(end) - range.0.max(start)) + 1;
     }
-    println!("Fresh ingredient ranges: {:?}", fresh_ranges);
-    let mut total_ids = 0;
-    for range in fresh_ranges {
-        total_ids += range.1 - range.0 + 1;
+    return total_ids;
+}
+
+// Answer "ID" (is it fresh?) or "START-END" (how many fresh IDs are there?)
+fn answer_query(query: &str, fresh_ranges: &Vec<Range>) -> Result<String, String> {
+    let query = query.trim();
+    if query.contains("-") {
+        let (start, end) = parse_range(query)?;
+        let fresh_ids = count_ids_between(start, end, fresh_ranges);
+        return Ok(format!("{start}-{end}: {fresh_ids} fresh"));
+    }
+    let id: u64 = query
+        .parse()
+        .map_err(|_| format!("Invalid query '{query}', expected ID or START-END"))?;
+    let status = if in_ranges(id, fresh_ranges) {
+        "fresh"
+    } else {
+        "stale"
+    };
+    return Ok(format!("{id}: {status}"));
+}
+
+/**
+ * Usage: cargo run 5 query RANGES_FILE [QUERY_FILE]
+ *
+ * Loads the fresh ranges, then answers queries line by line from QUERY_FILE,
+ * or from stdin as they're entered. A query is either an ID, to check if
+ * it's fresh, or START-END, to count the fresh IDs in that range.
+ */
+pub fn query() {
+    let ranges_path = args::get_arg(3).expect("Usage: query RANGES_FILE [QUERY_FILE]");
+    let contents = fs::read_to_string(&ranges_path).expect("Failed to read ranges file");
+    let range_lines = contents.lines().map(|line| line.to_string()).collect();
+    let fresh_ranges = load_ranges(range_lines).unwrap_or_else(|err| panic!("{err}"));
+    let fresh_ranges = merge_ranges(fresh_ranges);
+    println!(
+        "Loaded {} fresh range(s) ({} IDs)",
+        fresh_ranges.len(),
+        count_ids(&fresh_ranges)
+    );
+
+    let answer = |query: &str| {
+        if query.trim() == "" {
+            return;
+        }
+        match answer_query(query, &fresh_ranges) {
+            Ok(answer) => println!("{answer}"),
+            Err(err) => println!("Error: {err}"),
+        }
+    };
+    match args::get_arg(4) {
+        Some(query_path) => {
+            let queries = fs::read_to_string(&query_path).expect("Failed to read query file");
+            for query in queries.lines() {
+                answer(query);
+            }
+        }
+        None => loop {
+            let (bytes, query) = lines::read_line();
+            if bytes < 1 {
+                break;
+            }
+            answer(&query);
+        },
     }
-    println!("Fresh ingredient IDs: {:?}", total_ids);
 }
 
 #[cfg(test)]
@@ -152,5 +249,204 @@ mod tests {
         assert_eq!(ranges_overlap(&(3, 5), &(2, 6)), (true, 2, 6)); // complete overlap
         assert_eq!(ranges_overlap(&(1, 3), &(5, 6)).0, false); // no overlap
         assert_eq!(ranges_overlap(&(5, 6), &(1, 3)).0, false); // no overlap
+        assert_eq!(ranges_overlap(&(3, 5), &(1, 2)), (true, 1, 5)); // contiguous, reversed
+        assert_eq!(ranges_overlap(&(1, 10), &(2, 3)), (true, 1, 10)); // contained
+        assert_eq!(ranges_overlap(&(5, u64::MAX), &(1, 4)), (true, 1, u64::MAX));
+        assert_eq!(
+            ranges_overlap(&(1, u64::MAX), &(u64::MAX, u64::MAX)),
+            (true, 1, u64::MAX)
+        );
+        assert_eq!(ranges_overlap(&(0, 1), &(u64::MAX, u64::MAX)).0, false);
+    }
+
+    #[test]
+    fn test_load_ranges() {
+        let lines = vec!["3-5".to_string(), "0-18446744073709551615".to_string()];
+        assert_eq!(load_ranges(lines), Ok(vec![(3, 5), (0, u64::MAX)]));
+        let lines = vec!["3-5".to_string(), "7-7".to_string(), "9-8".to_string()];
+        assert_eq!(
+            load_ranges(lines),
+            Err("Line 3: Range '9-8' starts after it ends".to_string())
+        );
+        assert!(load_ranges(vec!["1-x".to_string()]).is_err());
+        assert!(load_ranges(vec!["1-18446744073709551616".to_string()]).is_err());
+
+        let full = merge_ranges(vec![(0, 10), (11, u64::MAX)]);
+        assert_eq!(full, vec![(0, u64::MAX)]);
+        assert_eq!(count_ids(&full), 1u128 << 64);
+    }
+
+    // Pseudo-random number generator, so property tests are repeatable
+    fn next_random(seed: &mut u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        return *seed >> 11;
+    }
+
+    // Random range, near zero, near u64::MAX or anywhere
+    fn random_range(seed: &mut u64) -> Range {
+        let len = next_random(seed) % 10;
+        let start = match next_random(seed) % 3 {
+            0 => next_random(seed) % 20,
+            1 => u64::MAX - next_random(seed) % 20,
+            _ => next_random(seed) << 11,
+        };
+        return (start, start.saturating_add(len));
+    }
+
+    #[test]
+    fn test_range_properties() {
+        let mut seed = 42;
+        for _ in 0..1000 {
+            let range1 = random_range(&mut seed);
+            let range2 = random_range(&mut seed);
+            let (overlap, start, end) = ranges_overlap(&range1, &range2);
+            let (overlap_rev, start_rev, end_rev) = ranges_overlap(&range2, &range1);
+
+            // Symmetric
+            assert_eq!(overlap, overlap_rev);
+            if overlap {
+                assert_eq!((start, end), (start_rev, end_rev));
+                // Merged range covers both, and nothing outside them
+                assert_eq!(start, range1.0.min(range2.0));
+                assert_eq!(end, range1.1.max(range2.1));
+            } else {
+                // A gap of at least one ID between them
+                let (first, second) = if range1.0 < range2.0 {
+                    (range1, range2)
+                } else {
+                    (range2, range1)
+                };
+                assert!(first.1 < second.0 - 1);
+            }
+        }
+
+        for _ in 0..200 {
+            let ranges: Vec<Range> = (0..6).map(|_| random_range(&mut seed)).collect();
+            let merged = merge_ranges(ranges.clone());
+
+            // Sorted, with gaps between them
+            for pair in merged.windows(2) {
+                assert!(pair[0].1 < pair[1].0 - 1);
+            }
+            // Every input range is inside exactly one merged range
+            for range in &ranges {
+                let containing = merged
+                    .iter()
+                    .filter(|m| m.0 <= range.0 && range.1 <= m.1)
+                    .count();
+                assert_eq!(containing, 1);
+            }
+            // Merging again changes nothing
+            assert_eq!(merge_ranges(merged.clone()), merged);
+        }
+    }
+
+    // Merge by repeatedly combining any pair of ranges which overlap
+    fn merge_pairwise(mut fresh_ranges: Vec<Range>) -> Vec<Range> {
+        loop {
+            let mut merged = vec![];
+            for i in 0..fresh_ranges.len() {
+                for j in 0..fresh_ranges.len() {
+                    if i == j {
+                        continue;
+                    }
+                    let (overlap, new_start, new_end) =
+                        ranges_overlap(&fresh_ranges[i], &fresh_ranges[j]);
+                    if overlap {
+                        fresh_ranges[i].0 = new_start;
+                        fresh_ranges[i].1 = new_end;
+                        merged.push(j);
+                    }
+                }
+                if merged.len() > 0 {
+                    break;
+                }
+            }
+            if merged.len() > 0 {
+                merged.reverse();
+                for i in merged {
+                    fresh_ranges.remove(i);
+                }
+            } else {
+                break;
+            }
+        }
+        fresh_ranges.sort();
+        return fresh_ranges;
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        assert_eq!(
+            merge_ranges(vec![(3, 5), (10, 14), (16, 20), (12, 18)]),
+            vec![(3, 5), (10, 20)]
+        );
+        assert_eq!(merge_ranges(vec![(5, 6), (1, 2), (3, 4)]), vec![(1, 6)]); // contiguous
+        assert_eq!(merge_ranges(vec![(1, 10), (2, 3), (4, 5)]), vec![(1, 10)]); // contained
+        assert_eq!(merge_ranges(vec![(1, 2), (4, 5)]), vec![(1, 2), (4, 5)]);
+        assert_eq!(merge_ranges(vec![]), vec![]);
+
+        // Cross-check against pairwise merging on pseudo-random ranges
+        let mut seed: u64 = 12345;
+        for _ in 0..200 {
+            let mut ranges = vec![];
+            for _ in 0..8 {
+                let start = next_random(&mut seed) % 50;
+                let len = next_random(&mut seed) % 8;
+                ranges.push((start, start + len));
+            }
+            assert_eq!(merge_ranges(ranges.clone()), merge_pairwise(ranges));
+        }
+    }
+
+    #[test]
+    fn test_count_fresh() {
+        let lines: Vec<String> = [
+            "3-5", "10-14", "16-20", "12-18", "", "1", "5", "8", "11", "17", "32",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        assert_eq!(count_fresh(lines.clone()), 3);
+
+        // Order of lines doesn't matter
+        let mut reversed = lines;
+        reversed.reverse();
+        assert_eq!(count_fresh(reversed), 3);
+
+        let ranges = vec![(3, 5), (10, 20), (30, u64::MAX)];
+        let fresh: Vec<u64> = [0, 2, 3, 4, 5, 6, 9, 10, 20, 21, 29, 30, u64::MAX]
+            .into_iter()
+            .filter(|ingredient| is_fresh(*ingredient, &ranges))
+            .collect();
+        assert_eq!(fresh, vec![3, 4, 5, 10, 20, 30, u64::MAX]);
+        assert!(!is_fresh(1, &vec![]));
+    }
+
+    #[test]
+    fn test_query() {
+        let ranges = merge_ranges(vec![(3, 5), (10, 14), (16, 20), (12, 18)]);
+        assert_eq!(answer_query("5", &ranges), Ok("5: fresh".to_string()));
+        assert_eq!(answer_query(" 8 ", &ranges), Ok("8: stale".to_string()));
+        assert_eq!(
+            answer_query("1-100", &ranges),
+            Ok("1-100: 14 fresh".to_string())
+        );
+        assert_eq!(
+            answer_query("4-12", &ranges),
+            Ok("4-12: 5 fresh".to_string())
+        );
+        assert_eq!(answer_query("6-9", &ranges), Ok("6-9: 0 fresh".to_string()));
+        assert_eq!(
+            answer_query("20-20", &ranges),
+            Ok("20-20: 1 fresh".to_string())
+        );
+        assert!(answer_query("9-4", &ranges).is_err());
+        assert!(answer_query("fresh?", &ranges).is_err());
+
+        let everything = vec![(0, u64::MAX)];
+        assert_eq!(count_ids_between(0, u64::MAX, &everything), 1u128 << 64);
     }
 }
@@ -1,198 +1,649 @@
-const DEBUG_OUTPUT: bool = true;
+use crate::args;
+use crate::output;
 
-enum NumOrOp {
-    Number(u64),
-    Op(char),
-}
+const DEBUG_OUTPUT: bool = true;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum OpType {
     Add,
+    Subtract,
     Multiply,
+    Divide,
+    Min,
+    Max,
+    Power,
+    Concat,
 }
 
-fn load_sheet(lines: Vec<String>) -> Vec<Vec<NumOrOp>> {
-    let mut sheet: Vec<Vec<NumOrOp>> = vec![];
-    let mut row: usize = 0;
-    for line in lines {
-        let parts = line.split_ascii_whitespace();
-        let mut col = 0;
-        for part in parts {
-            // Ignore whitespace at start and end of line
-            if part == "" {
-                continue;
-            }
+// Symbol used for each operation in the worksheet
+const OP_TABLE: [(char, OpType); 8] = [
+    ('+', OpType::Add),
+    ('-', OpType::Subtract),
+    ('*', OpType::Multiply),
+    ('/', OpType::Divide),
+    ('<', OpType::Min),
+    ('>', OpType::Max),
+    ('^', OpType::Power),
+    ('|', OpType::Concat),
+];
 
-            if row == 0 {
-                sheet.push(vec![]);
+impl OpType {
+    fn from_symbol(symbol: char) -> Option<OpType> {
+        for (op_symbol, op) in OP_TABLE {
+            if op_symbol == symbol {
+                return Some(op);
             }
-            if part == "*" || part == "+" {
-                sheet[col].push(NumOrOp::Op(part.chars().nth(0).unwrap()));
-            } else {
-                let num = part.parse().expect("Must be a number");
-                sheet[col].push(NumOrOp::Number(num));
-            }
-            col += 1;
         }
-        row += 1;
+        return None;
     }
-    return sheet;
-}
 
-// Add up numbers in columns
-fn col_sum(op: char, col: &[NumOrOp]) -> u64 {
-    let mut sum = match col[0] {
-        NumOrOp::Number(num) => num,
-        _ => panic!("Must be a number"),
-    };
+    fn symbol(&self) -> char {
+        for (op_symbol, op) in OP_TABLE {
+            if op == *self {
+                return op_symbol;
+            }
+        }
+        panic!("Missing symbol for {:?}", self);
+    }
 
-    for i in 1..col.len() {
-        let num = match col[i] {
-            NumOrOp::Number(x) => x,
-            _ => panic!("Must be a number"),
+    // Combine the running result with the next operand
+    fn apply(&self, left: u64, right: u64) -> Result<u64, String> {
+        return match self {
+            OpType::Add => left.checked_add(right).ok_or(self.overflow(left, right)),
+            OpType::Subtract => left.checked_sub(right).ok_or(self.overflow(left, right)),
+            OpType::Multiply => left.checked_mul(right).ok_or(self.overflow(left, right)),
+            OpType::Divide => match right {
+                0 => Err(format!("Division by zero ({left} / 0)")),
+                _ => Ok(left / right),
+            },
+            OpType::Min => Ok(left.min(right)),
+            OpType::Max => Ok(left.max(right)),
+            OpType::Power => {
+                let exp =
+                    u32::try_from(right).map_err(|_| format!("Exponent {right} too large"))?;
+                left.checked_pow(exp).ok_or(self.overflow(left, right))
+            }
+            OpType::Concat => 10u64
+                .checked_pow(right.to_string().len() as u32)
+                .and_then(|shift| left.checked_mul(shift))
+                .and_then(|shifted| shifted.checked_add(right))
+                .ok_or(self.overflow(left, right)),
         };
-        if op == '*' {
-            sum *= num;
-        } else {
-            sum += num;
-        }
     }
 
-    if DEBUG_OUTPUT {
-        println!("Col sum: {sum}");
+    fn overflow(&self, left: u64, right: u64) -> String {
+        return format!("{left} {} {right} overflows", self.symbol());
     }
+}
+
+// Apply an operation to a list of operands, from left to right
+fn evaluate(op: OpType, operands: &[u64]) -> Result<u64, String> {
+    if operands.len() == 0 {
+        return Err(format!("No operands for '{}'", op.symbol()));
+    }
+    let mut result = operands[0];
+    for operand in &operands[1..] {
+        result = op.apply(result, *operand)?;
+    }
+    return Ok(result);
+}
+
+// A problem on the worksheet: an operation to apply to a list of operands
+struct Problem {
+    op: OpType,
+    operands: Vec<u64>,
+}
 
-    return sum;
+// How the numbers in each problem are read
+#[derive(Clone, Copy)]
+enum Reading {
+    Rows,    // Each row is a number, split on whitespace
+    Columns, // Cephalopod-style: each column is a number, read right to left
 }
 
-fn load_sheet2(lines: Vec<String>) -> Vec<(OpType, Vec<u64>)> {
-    let mut columns = vec![];
-    let last_line = &lines[lines.len() - 1];
-    let mut col_starts = vec![];
-    let mut idx = 0;
-    for char in last_line.chars() {
-        let op_type;
-        match char {
-            '*' => {
-                op_type = OpType::Multiply;
-                col_starts.push(idx);
-                columns.push((op_type, vec![]));
+const TAB_WIDTH: usize = 8;
+
+// Expand tabs to the next tab stop, so they line up as they would in an editor
+fn expand_tabs(line: &str) -> Vec<char> {
+    let mut chars = vec![];
+    for char in line.chars() {
+        if char == '\t' {
+            chars.push(' ');
+            while chars.len() % TAB_WIDTH != 0 {
+                chars.push(' ');
             }
-            '+' => {
-                op_type = OpType::Add;
-                col_starts.push(idx);
-                columns.push((op_type, vec![]));
+        } else if char != '\r' {
+            chars.push(char);
+        }
+    }
+    return chars;
+}
+
+// Operation for a block of columns, which can be anywhere in the last row
+fn block_op(op_row: &Vec<char>, start: usize, end: usize) -> Result<OpType, String> {
+    let mut op = None;
+    for col in start..end {
+        let symbol = op_row[col];
+        if symbol == ' ' {
+            continue;
+        }
+        let op_type = OpType::from_symbol(symbol).ok_or(format!(
+            "Invalid operation '{symbol}' in column {}",
+            col + 1
+        ))?;
+        if op.is_some() {
+            return Err(format!(
+                "More than one operation in columns {}-{end}",
+                start + 1
+            ));
+        }
+        op = Some(op_type);
+    }
+    return op.ok_or(format!("No operation for columns {}-{end}", start + 1));
+}
+
+// Each column of the block is a number, read top to bottom, and the numbers are read right to left
+fn read_columns(rows: &[Vec<char>], start: usize, end: usize) -> Result<Vec<u64>, String> {
+    let mut operands = vec![];
+    for col in (start..end).rev() {
+        let mut digits = String::new();
+        for (row_num, row) in rows.iter().enumerate() {
+            let char = row[col];
+            if char.is_ascii_digit() {
+                digits.push(char);
+            } else if char != ' ' {
+                return Err(format!(
+                    "Invalid character '{char}' at row {}, column {}",
+                    row_num + 1,
+                    col + 1
+                ));
             }
-            ' ' => {}
-            _ => panic!("Invalid character in op row"),
         }
-        idx += 1;
+        if digits == "" {
+            continue;
+        }
+        let operand = digits
+            .parse()
+            .map_err(|_| format!("Number {digits} in column {} is too large", col + 1))?;
+        operands.push(operand);
     }
-    let num_cols = columns.len();
-    let line_len = last_line.len();
+    return Ok(operands);
+}
 
-    let mut col_strings = vec![];
-    for _ in 0..=line_len - col_starts.len() {
-        col_strings.push("".to_string());
+/**
+ * Read the problems row by row, splitting each row on whitespace. The Nth
+ * entry in each row belongs to the Nth problem, and the operations are in
+ * the last row. Every row must have one entry per problem; blank lines are ignored.
+ */
+fn split_rows(lines: &Vec<String>) -> Result<Vec<Problem>, String> {
+    let mut rows = vec![];
+    for (line_num, line) in lines.iter().enumerate() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() > 0 {
+            rows.push((line_num, parts));
+        }
+    }
+    if rows.len() < 2 {
+        return Err(
+            "Worksheet needs at least one row of numbers and a row of operations".to_string(),
+        );
     }
+    let ((op_line, op_parts), num_rows) = rows.split_last().unwrap();
 
-    if DEBUG_OUTPUT {
-        println!("Last line: '{last_line}'");
-        println!("Line len: {line_len}, num cols: {num_cols}");
+    let mut problems = vec![];
+    for symbol in op_parts {
+        let mut chars = symbol.chars();
+        let op = match (chars.next(), chars.next()) {
+            (Some(char), None) => OpType::from_symbol(char),
+            _ => None,
+        };
+        let op = op.ok_or(format!(
+            "Invalid operation '{symbol}' at row {}",
+            op_line + 1
+        ))?;
+        problems.push(Problem {
+            op,
+            operands: vec![],
+        });
     }
+    for (line_num, parts) in num_rows {
+        if parts.len() != problems.len() {
+            return Err(format!(
+                "Row {} has {} numbers, but there are {} operations",
+                line_num + 1,
+                parts.len(),
+                problems.len()
+            ));
+        }
+        for (idx, part) in parts.iter().enumerate() {
+            let operand = part.parse().map_err(|_| {
+                format!(
+                    "Invalid number '{part}' at row {}, problem {}",
+                    line_num + 1,
+                    idx + 1
+                )
+            })?;
+            problems[idx].operands.push(operand);
+        }
+    }
+    return Ok(problems);
+}
 
-    for line_num in 0..lines.len() - 1 {
-        let line = &lines[line_num];
+/**
+ * Read the problems column by column. Each problem is a block of columns,
+ * with blocks separated by columns which are blank in every row, and its
+ * operation is in the last row, anywhere in the block.
+ *
+ * Short lines are padded with spaces, and tabs are expanded.
+ */
+fn split_blocks(lines: &Vec<String>) -> Result<Vec<Problem>, String> {
+    let mut rows: Vec<Vec<char>> = lines.iter().map(|line| expand_tabs(line)).collect();
+    while rows
+        .last()
+        .is_some_and(|row| row.iter().all(|ch| *ch == ' '))
+    {
+        rows.pop();
+    }
+    if rows.len() < 2 {
+        return Err(
+            "Worksheet needs at least one row of numbers and a row of operations".to_string(),
+        );
+    }
+    let width = rows.iter().map(|row| row.len()).max().unwrap();
+    for row in &mut rows {
+        row.resize(width, ' ');
+    }
+    let (op_row, num_rows) = rows.split_last().unwrap();
 
-        let mut col_num = col_strings.len() - 1;
-        let mut line_pos = line_len;
-        let mut skip_col = false;
-        for char in line.chars().rev() {
-            line_pos -= 1;
-            if skip_col {
-                skip_col = false;
-                continue;
-            }
-            col_strings[col_num].push(char);
-            if col_starts.contains(&line_pos) {
-                skip_col = true;
-            }
-            if col_num == 0 {
-                break;
+    // Runs of columns which aren't blank all the way down
+    let mut blocks = vec![];
+    let mut block_start = None;
+    for col in 0..=width {
+        let blank = col == width || rows.iter().all(|row| row[col] == ' ');
+        match (blank, block_start) {
+            (false, None) => block_start = Some(col),
+            (true, Some(start)) => {
+                blocks.push((start, col));
+                block_start = None;
             }
-            col_num -= 1;
+            _ => (),
         }
     }
 
-    if DEBUG_OUTPUT {
-        println!("Col strings: {:?}", col_strings);
+    let mut problems = vec![];
+    for (start, end) in blocks {
+        let op = block_op(op_row, start, end)?;
+        let operands = read_columns(num_rows, start, end)?;
+        problems.push(Problem { op, operands });
     }
 
-    let mut offset = 0;
-    for col_num in 0..num_cols {
-        let col_start = col_starts[col_num];
-        let col_end: usize = if col_num < num_cols - 1 {
-            col_starts[col_num + 1] - 1
+    return Ok(problems);
+}
+
+// Read the problems on the worksheet, the same model for either reading
+fn load_sheet(lines: Vec<String>, reading: Reading) -> Result<Vec<Problem>, String> {
+    return match reading {
+        Reading::Rows => split_rows(&lines),
+        Reading::Columns => split_blocks(&lines),
+    };
+}
+
+// One row per problem: number, operation, then operands and result for each part
+// A part whose sheet couldn't be read has its error in each of its result columns
+fn export_rows(
+    problems1: Result<&Vec<Problem>, &String>,
+    problems2: Result<&Vec<Problem>, &String>,
+) -> Vec<Vec<String>> {
+    let describe = |problems: Result<&Vec<Problem>, &String>, idx: usize| {
+        let problem = match problems {
+            Ok(problems) => problems.get(idx),
+            Err(err) => return ("".to_string(), "".to_string(), format!("error: {err}")),
+        };
+        let Some(problem) = problem else {
+            return ("".to_string(), "".to_string(), "".to_string());
+        };
+        let operands: Vec<String> = problem.operands.iter().map(|num| num.to_string()).collect();
+        let result = match evaluate(problem.op, &problem.operands) {
+            Ok(result) => result.to_string(),
+            Err(err) => format!("error: {err}"),
+        };
+        return (problem.op.symbol().to_string(), operands.join(" "), result);
+    };
+
+    let mut rows = vec![];
+    let num_problems = |problems: Result<&Vec<Problem>, &String>| problems.map_or(0, |p| p.len());
+    for idx in 0..num_problems(problems1).max(num_problems(problems2)) {
+        let (op1, operands1, result1) = describe(problems1, idx);
+        let (op2, operands2, result2) = describe(problems2, idx);
+        // Both parts read the same operation row, so flag it if they disagree
+        let op = if op1 == op2 || op2 == "" {
+            op1
+        } else if op1 == "" {
+            op2
         } else {
-            line_len
+            format!("{op1}/{op2}")
         };
-        let col_width = col_end - col_start;
-        for col_pos in 0..col_width {
-            let pos = offset + col_pos;
-            let val: u64 = col_strings[pos]
-                .trim()
-                .parse()
-                .expect("Failed to parse number");
-            columns[col_num].1.push(val);
-        }
-        offset += col_width;
+        rows.push(vec![
+            (idx + 1).to_string(),
+            op,
+            operands1,
+            result1,
+            operands2,
+            result2,
+        ]);
     }
+    return rows;
+}
 
-    if DEBUG_OUTPUT {
-        println!("Columns:");
-        for column in &columns {
-            let op = match column.0 {
-                OpType::Add => '+',
-                OpType::Multiply => '*',
-            };
-            println!("    {}: {:?}", op, column.1);
-        }
+const EXPORT_HEADER: [&str; 6] = [
+    "problem",
+    "op",
+    "part1_operands",
+    "part1_result",
+    "part2_operands",
+    "part2_result",
+];
+
+/**
+ * Options:
+ *   --csv[=FILE]    Export each problem, as read for both parts, as CSV
+ *   --table[=FILE]  Export each problem, as read for both parts, as an aligned table
+ *
+ * Given the problems already read for this part, reads the sheet the other
+ * way for the other part. Returns whether anything was exported to stdout.
+ */
+fn export(lines: &Vec<String>, reading: Reading, problems: &Vec<Problem>) -> bool {
+    let csv_path = args::get_option("csv");
+    let table_path = args::get_option("table");
+    if csv_path.is_none() && table_path.is_none() {
+        return false;
     }
+    let other_reading = match reading {
+        Reading::Rows => Reading::Columns,
+        Reading::Columns => Reading::Rows,
+    };
+    let other_problems = load_sheet(lines.clone(), other_reading);
+    let rows = match reading {
+        Reading::Rows => export_rows(Ok(problems), other_problems.as_ref()),
+        Reading::Columns => export_rows(other_problems.as_ref(), Ok(problems)),
+    };
+    let mut to_stdout = false;
+    if let Some(path) = csv_path {
+        output::emit(&path, &output::csv(&EXPORT_HEADER, &rows));
+        to_stdout |= output::is_stdout(&path);
+    }
+    if let Some(path) = table_path {
+        output::emit(&path, &output::table(&EXPORT_HEADER, &rows));
+        to_stdout |= output::is_stdout(&path);
+    }
+    return to_stdout;
+}
 
-    return columns;
+// Add a column's result to the running total, saying which column went wrong
+fn add_column(total: u64, col_num: usize, result: Result<u64, String>) -> Result<u64, String> {
+    let sum = result.map_err(|err| format!("Column {}: {err}", col_num + 1))?;
+    return total.checked_add(sum).ok_or(format!(
+        "Column {}: total {total} + {sum} overflows",
+        col_num + 1
+    ));
 }
 
-// Add up numbers in cephalopod columns
-fn col_sum2(op: OpType, col: Vec<u64>) -> u64 {
-    let mut result = col[0];
-    for i in 1..col.len() {
-        match op {
-            OpType::Add => result += col[i],
-            OpType::Multiply => result *= col[i],
-        }
+fn total_sum(problems: &Vec<Problem>) -> Result<u64, String> {
+    let mut total_sum = 0;
+    for (col_num, problem) in problems.iter().enumerate() {
+        total_sum = add_column(total_sum, col_num, evaluate(problem.op, &problem.operands))?;
     }
-    return result;
+    return Ok(total_sum);
 }
 
-pub fn part1(lines: Vec<String>) {
-    let sheet = load_sheet(lines);
-    let mut total_sum = 0;
-    for col in sheet {
-        let op = col.last().unwrap();
-        match op {
-            NumOrOp::Op(ch) => {
-                total_sum += col_sum(*ch, &col[0..col.len() - 1]);
-            }
-            _ => panic!("Last row must be operation"),
+fn run(lines: Vec<String>, reading: Reading) {
+    let problems = match load_sheet(lines.clone(), reading) {
+        Ok(problems) => problems,
+        Err(err) => {
+            println!("Error: {err}");
+            return;
+        }
+    };
+    // Keep anything exported to stdout clean of everything else
+    if export(&lines, reading, &problems) {
+        return;
+    }
+    if DEBUG_OUTPUT {
+        println!("Problems:");
+        for problem in &problems {
+            println!("    {}: {:?}", problem.op.symbol(), problem.operands);
         }
     }
-    println!("Total sum: {total_sum}");
+    match total_sum(&problems) {
+        Ok(total_sum) => println!("Total sum: {total_sum}"),
+        Err(err) => println!("Error: {err}"),
+    }
+}
+
+pub fn part1(lines: Vec<String>) {
+    run(lines, Reading::Rows);
 }
 
 pub fn part2(lines: Vec<String>) {
-    let sheet = load_sheet2(lines);
-    let mut total_sum = 0;
-    for (op, col) in sheet {
-        total_sum += col_sum2(op, col);
+    run(lines, Reading::Columns);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate(OpType::Add, &[1, 2, 3]), Ok(6));
+        assert_eq!(evaluate(OpType::Subtract, &[10, 2, 3]), Ok(5));
+        assert_eq!(evaluate(OpType::Multiply, &[2, 3, 4]), Ok(24));
+        assert_eq!(evaluate(OpType::Divide, &[100, 5, 3]), Ok(6));
+        assert_eq!(evaluate(OpType::Min, &[4, 2, 9]), Ok(2));
+        assert_eq!(evaluate(OpType::Max, &[4, 2, 9]), Ok(9));
+        assert_eq!(evaluate(OpType::Power, &[2, 3, 2]), Ok(64));
+        assert_eq!(evaluate(OpType::Concat, &[12, 0, 345]), Ok(120345));
+        assert_eq!(evaluate(OpType::Add, &[7]), Ok(7));
+        assert_eq!(
+            evaluate(OpType::Divide, &[5, 0]),
+            Err("Division by zero (5 / 0)".to_string())
+        );
+        assert!(evaluate(OpType::Add, &[]).is_err());
+    }
+
+    #[test]
+    fn test_op_table() {
+        for (symbol, op) in OP_TABLE {
+            assert_eq!(OpType::from_symbol(symbol), Some(op));
+            assert_eq!(op.symbol(), symbol);
+        }
+        assert_eq!(OpType::from_symbol('%'), None);
+    }
+
+    #[test]
+    fn test_load_sheet() {
+        let lines = to_lines(&["12 3 20", " 4 5  4", " - < /"]);
+        let problems = load_sheet(lines.clone(), Reading::Rows).unwrap();
+        assert_eq!(
+            summary(problems),
+            vec![('-', vec![12, 4]), ('<', vec![3, 5]), ('/', vec![20, 4])]
+        );
+        let results: Vec<Result<u64, String>> = load_sheet(lines, Reading::Rows)
+            .unwrap()
+            .iter()
+            .map(|problem| evaluate(problem.op, &problem.operands))
+            .collect();
+        assert_eq!(results, vec![Ok(8), Ok(3), Ok(5)]);
+
+        let example = to_lines(&[
+            "123 328  51 64 ",
+            " 45 64  387 23 ",
+            "  6 98  215 314",
+            "*   +   *   +  ",
+        ]);
+        assert_eq!(sheet_total(example, Reading::Rows), Ok(4277556));
+        assert_eq!(
+            load_sheet(to_lines(&["1 2x", "+  +"]), Reading::Rows).err(),
+            Some("Invalid number '2x' at row 1, problem 2".to_string())
+        );
+
+        // Problems don't need blank columns between them when read by row
+        let unaligned = to_lines(&["1 22", "333 4", "+ *"]);
+        assert_eq!(
+            summary(load_sheet(unaligned.clone(), Reading::Rows).unwrap()),
+            vec![('+', vec![1, 333]), ('*', vec![22, 4])]
+        );
+        assert_eq!(sheet_total(unaligned, Reading::Rows), Ok(422));
+        assert_eq!(
+            load_sheet(to_lines(&["1 2 3", "+ *"]), Reading::Rows).err(),
+            Some("Row 1 has 3 numbers, but there are 2 operations".to_string())
+        );
+        assert_eq!(
+            load_sheet(to_lines(&["1 2 3", "4 5", "+ * +"]), Reading::Rows).err(),
+            Some("Row 2 has 2 numbers, but there are 3 operations".to_string())
+        );
+        assert_eq!(
+            load_sheet(to_lines(&["1 2", "+ **"]), Reading::Rows).err(),
+            Some("Invalid operation '**' at row 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = u64::MAX / 2 + 1;
+        assert_eq!(
+            evaluate(OpType::Multiply, &[big, 2]),
+            Err(format!("{big} * 2 overflows"))
+        );
+        assert_eq!(evaluate(OpType::Add, &[big, big - 1]), Ok(u64::MAX));
+        assert!(evaluate(OpType::Add, &[big, big]).is_err());
+        assert_eq!(
+            evaluate(OpType::Subtract, &[3, 5]),
+            Err("3 - 5 overflows".to_string())
+        );
+        assert!(evaluate(OpType::Power, &[10, 20]).is_err());
+        assert_eq!(evaluate(OpType::Power, &[10, 19]), Ok(10u64.pow(19)));
+        assert!(evaluate(OpType::Concat, &[1844674407, 3709551616]).is_err());
+        assert_eq!(
+            evaluate(OpType::Concat, &[1844674407, 3709551615]),
+            Ok(u64::MAX)
+        );
+
+        let lines = vec![
+            "1 4294967296".to_string(),
+            "2 4294967296".to_string(),
+            "+ *".to_string(),
+        ];
+        assert_eq!(
+            sheet_total(lines, Reading::Rows),
+            Err("Column 2: 4294967296 * 4294967296 overflows".to_string())
+        );
+        let lines = vec!["1 18446744073709551615".to_string(), "+ +".to_string()];
+        assert_eq!(
+            sheet_total(lines, Reading::Rows),
+            Err("Column 2: total 1 + 18446744073709551615 overflows".to_string())
+        );
+    }
+
+    fn sheet_total(lines: Vec<String>, reading: Reading) -> Result<u64, String> {
+        return total_sum(&load_sheet(lines, reading)?);
+    }
+
+    fn to_lines(lines: &[&str]) -> Vec<String> {
+        return lines.iter().map(|line| line.to_string()).collect();
+    }
+
+    fn summary(problems: Vec<Problem>) -> Vec<(char, Vec<u64>)> {
+        return problems
+            .iter()
+            .map(|problem| (problem.op.symbol(), problem.operands.clone()))
+            .collect();
+    }
+
+    #[test]
+    fn test_load_sheet_columns() {
+        let example = to_lines(&[
+            "123 328  51 64 ",
+            " 45 64  387 23 ",
+            "  6 98  215 314",
+            "*   +   *   +  ",
+        ]);
+        assert_eq!(
+            summary(load_sheet(example.clone(), Reading::Columns).unwrap()),
+            vec![
+                ('*', vec![356, 24, 1]),
+                ('+', vec![8, 248, 369]),
+                ('*', vec![175, 581, 32]),
+                ('+', vec![4, 431, 623]),
+            ]
+        );
+        assert_eq!(sheet_total(example, Reading::Columns), Ok(3263827));
+
+        // Trailing spaces trimmed, short lines, and a blank line at the end
+        let ragged = to_lines(&[
+            "123 328  51 64",
+            " 45 64  387 23",
+            "  6 98  215 314",
+            "*   +   *   +",
+            "",
+        ]);
+        assert_eq!(sheet_total(ragged, Reading::Columns), Ok(3263827));
+
+        // Tabs expanded to the next multiple of 8
+        let tabbed = to_lines(&["12\t3", " 4\t5", "+\t-"]);
+        assert_eq!(
+            summary(load_sheet(tabbed, Reading::Columns).unwrap()),
+            vec![('+', vec![24, 1]), ('-', vec![35])]
+        );
+    }
+
+    #[test]
+    fn test_load_sheet_columns_errors() {
+        assert_eq!(
+            load_sheet(to_lines(&["12 3", "+  "]), Reading::Columns).err(),
+            Some("No operation for columns 4-4".to_string())
+        );
+        assert_eq!(
+            load_sheet(to_lines(&["12 3", "+* +"]), Reading::Columns).err(),
+            Some("More than one operation in columns 1-2".to_string())
+        );
+        assert_eq!(
+            load_sheet(to_lines(&["1x 3", "+  +"]), Reading::Columns).err(),
+            Some("Invalid character 'x' at row 1, column 2".to_string())
+        );
+        assert!(load_sheet(to_lines(&["12 3", "+  %"]), Reading::Columns).is_err());
+        assert!(load_sheet(to_lines(&["+"]), Reading::Columns).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let lines = to_lines(&["12 3 ", " 4 50", " * - "]);
+        let problems1 = load_sheet(lines.clone(), Reading::Rows).unwrap();
+        let problems2 = load_sheet(lines, Reading::Columns).unwrap();
+        let rows = export_rows(Ok(&problems1), Ok(&problems2));
+        assert_eq!(
+            output::csv(&EXPORT_HEADER, &rows),
+            "problem,op,part1_operands,part1_result,part2_operands,part2_result\n".to_string()
+                + "1,*,12 4,48,24 1,24\n"
+                + "2,-,3 50,error: 3 - 50 overflows,0 35,error: 0 - 35 overflows\n"
+        );
+        assert_eq!(
+            output::table(&EXPORT_HEADER, &rows),
+            "problem | op | part1_operands | part1_result            | part2_operands | part2_result\n"
+                .to_string()
+                + "1       | *  | 12 4           | 48                      | 24 1           | 24\n"
+                + "2       | -  | 3 50           | error: 3 - 50 overflows | 0 35           | error: 0 - 35 overflows\n"
+        );
+
+        // Part 1 reads this fine, but part 2 can't find the problems' columns
+        let unaligned = to_lines(&["1 22", "333 4", "+ *"]);
+        let problems1 = load_sheet(unaligned.clone(), Reading::Rows).unwrap();
+        let problems2 = load_sheet(unaligned, Reading::Columns);
+        let rows = export_rows(Ok(&problems1), problems2.as_ref());
+        assert_eq!(
+            output::csv(&EXPORT_HEADER, &rows),
+            "problem,op,part1_operands,part1_result,part2_operands,part2_result\n".to_string()
+                + "1,+,1 333,334,,error: More than one operation in columns 1-5\n"
+                + "2,*,22 4,88,,error: More than one operation in columns 1-5\n"
+        );
     }
-    println!("Total sum: {total_sum}");
 }
@@ -1,129 +1,649 @@
-use std::collections::HashMap;
+use crate::args;
+use crate::output;
 
 const DEBUG_OUTPUT: bool = false;
 
-pub fn part1(lines: Vec<String>) {
-    let mut beams = vec![];
-    let total_width = lines[0].len();
-    let mut total_splits = 0;
-    for line in lines {
-        let mut new_beams = vec![];
-        let mut pos = 0;
-        for char in line.chars() {
-            let mut split = false;
-            match char {
-                'S' => new_beams.push(pos),
-                '^' => {
-                    if beams.contains(&pos) {
-                        total_splits += 1;
-                        split = true;
-                        if pos > 0 {
-                            new_beams.push(pos - 1);
-                        }
-                        if pos < total_width - 1 {
-                            new_beams.push(pos + 1);
-                        }
-                    }
-                }
-                '.' => (),
-                _ => panic!("Unknown char"),
-            }
-            // Beam didn't hit a splitter; continue as normal
-            if !split && beams.contains(&pos) {
-                new_beams.push(pos);
-            }
-            pos += 1;
+// Set of beam positions across a row, one bit per column
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(len: usize) -> BitSet {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
         }
-        beams = new_beams;
     }
-    println!("Total splits: {total_splits}");
+
+    fn insert(&mut self, pos: usize) {
+        self.words[pos / 64] |= 1 << (pos % 64);
+    }
+
+    fn contains(&self, pos: usize) -> bool {
+        return self.words[pos / 64] >> (pos % 64) & 1 == 1;
+    }
+}
+
+/**
+ * Elements of the manifold. Each acts on beams arriving from the row above;
+ * beams it sends sideways land in the same row and carry on down from there.
+ *
+ *   .  Empty space
+ *   S  Source, emitting a new beam downwards
+ *   ^  Splitter, sending the beam to each of the split offsets
+ *   #  Absorber, stopping the beam
+ *   <  Deflector, moving the beam one column left
+ *   >  Deflector, moving the beam one column right
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Cell {
+    Empty,
+    Source,
+    Splitter,
+    Absorber,
+    DeflectLeft,
+    DeflectRight,
+}
+
+impl Cell {
+    fn from_char(char: char) -> Option<Cell> {
+        return match char {
+            '.' => Some(Cell::Empty),
+            'S' => Some(Cell::Source),
+            '^' => Some(Cell::Splitter),
+            '#' => Some(Cell::Absorber),
+            '<' => Some(Cell::DeflectLeft),
+            '>' => Some(Cell::DeflectRight),
+            _ => None,
+        };
+    }
+
+    fn symbol(&self) -> char {
+        return match self {
+            Cell::Empty => '.',
+            Cell::Source => 'S',
+            Cell::Splitter => '^',
+            Cell::Absorber => '#',
+            Cell::DeflectLeft => '<',
+            Cell::DeflectRight => '>',
+        };
+    }
 }
 
-struct Map {
-    grid: Vec<Vec<char>>,
+struct Manifold {
+    grid: Vec<Vec<Cell>>,
     width: usize,
     height: usize,
+    // Columns a splitter sends a beam to, relative to the splitter
+    split_offsets: Vec<isize>,
 }
 
-#[derive(Eq, Hash, PartialEq, Clone)]
-struct Pos {
-    row: usize,
-    col: usize,
-}
-
-fn load_grid(lines: Vec<String>) -> (Pos, Map) {
-    let mut grid: Vec<Vec<char>> = vec![];
-    let mut line_num = 0;
-    let mut start: Pos = Pos { row: 0, col: 0 };
-    let mut width = 0;
-    for line in lines {
-        grid.push(vec![]);
-        let mut char_num = 0;
-        for char in line.chars() {
-            grid[line_num].push(char);
-            if char == 'S' {
-                start.row = line_num;
-                start.col = char_num;
+impl Manifold {
+    // Short rows are padded with empty space
+    fn parse(lines: &Vec<String>, split_offsets: Vec<isize>) -> Result<Manifold, String> {
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut grid = vec![];
+        for (line_num, line) in lines.iter().enumerate() {
+            let mut row = vec![Cell::Empty; width];
+            for (col, char) in line.chars().enumerate() {
+                row[col] = Cell::from_char(char).ok_or(format!(
+                    "Line {}, column {}: Unknown element '{char}'",
+                    line_num + 1,
+                    col + 1
+                ))?;
             }
-            char_num += 1;
+            grid.push(row);
         }
-        if line_num == 0 {
-            width = char_num;
-        }
-        line_num += 1;
-    }
-    return (
-        start,
-        Map {
+        return Ok(Manifold {
+            height: grid.len(),
             grid,
             width,
-            height: line_num,
-        },
-    );
+            split_offsets,
+        });
+    }
+
+    fn shift(&self, col: usize, offset: isize) -> Option<usize> {
+        let target = col.checked_add_signed(offset)?;
+        if target >= self.width {
+            return None;
+        }
+        return Some(target);
+    }
+
+    // Columns a beam arriving from above at (row, col) carries on down from
+    fn next_columns(&self, row: usize, col: usize) -> Vec<usize> {
+        let offsets = match self.grid[row][col] {
+            Cell::Empty | Cell::Source => vec![0],
+            Cell::Splitter => self.split_offsets.clone(),
+            Cell::Absorber => vec![],
+            Cell::DeflectLeft => vec![-1],
+            Cell::DeflectRight => vec![1],
+        };
+        return offsets
+            .iter()
+            .filter_map(|offset| self.shift(col, *offset))
+            .collect();
+    }
+}
+
+// Split offsets, e.g. "-1,1" for the standard splitter or "-1,0,1" for a three-way one
+fn parse_split(spec: &str) -> Result<Vec<isize>, String> {
+    let mut offsets = vec![];
+    for part in spec.split(',') {
+        let offset: isize = part
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid split offset '{part}'"))?;
+        if !offsets.contains(&offset) {
+            offsets.push(offset);
+        }
+    }
+    return Ok(offsets);
+}
+
+/**
+ * Options:
+ *   --split=OFFSETS  Columns a splitter sends beams to, relative to it (default "-1,1")
+ */
+fn load_manifold(lines: Vec<String>) -> Manifold {
+    let split_offsets = match args::get_option("split") {
+        Some(spec) => parse_split(&spec).unwrap_or_else(|err| panic!("Invalid split: {err}")),
+        None => vec![-1, 1],
+    };
+    return Manifold::parse(&lines, split_offsets)
+        .unwrap_or_else(|err| panic!("Invalid manifold: {err}"));
 }
 
-fn count_timelines(start: Pos, map: &Map, known: &mut HashMap<Pos, u64>) -> u64 {
-    match known.get(&start) {
-        Some(value) => {
-            if DEBUG_OUTPUT {
-                println!("REPEAT: ({}, {})", start.row, start.col);
+/**
+ * Beams arriving at each row from the row above, moving down the manifold a
+ * row at a time. The last entry holds the beams leaving the bottom.
+ */
+fn trace_beams(manifold: &Manifold) -> Vec<BitSet> {
+    let mut rows = vec![BitSet::new(manifold.width)];
+    for row in 0..manifold.height {
+        let beams = &rows[row];
+        let mut new_beams = BitSet::new(manifold.width);
+        for col in 0..manifold.width {
+            if manifold.grid[row][col] == Cell::Source {
+                new_beams.insert(col);
+            }
+            if !beams.contains(col) {
+                continue;
+            }
+            for target in manifold.next_columns(row, col) {
+                new_beams.insert(target);
             }
-            return *value;
         }
-        None => (),
-    }
-    let mut pos = start.clone();
-    while pos.row < map.height - 1 {
-        pos.row += 1;
-        if map.grid[pos.row][pos.col] == '^' {
-            let mut split_positions = vec![];
-            if pos.col > 0 {
-                split_positions.push(Pos {
-                    row: pos.row,
-                    col: pos.col - 1,
-                });
+        rows.push(new_beams);
+    }
+    return rows;
+}
+
+// Number of times a beam hits a splitter
+fn count_splits(manifold: &Manifold) -> u64 {
+    let beams = trace_beams(manifold);
+    let mut total_splits = 0;
+    for row in 0..manifold.height {
+        for col in 0..manifold.width {
+            if manifold.grid[row][col] == Cell::Splitter && beams[row].contains(col) {
+                total_splits += 1;
             }
-            if pos.col < map.width - 1 {
-                split_positions.push(Pos {
-                    row: pos.row,
-                    col: pos.col + 1,
-                });
+        }
+    }
+    return total_splits;
+}
+
+// What to draw at each location, given the beams traced through the manifold
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Glyph {
+    Element(Cell),
+    Beam,
+    SplitterHit,
+    SplitterUnreached,
+}
+
+fn glyph_grid(manifold: &Manifold, beams: &Vec<BitSet>) -> Vec<Vec<Glyph>> {
+    let mut grid = vec![];
+    for row in 0..manifold.height {
+        let mut glyphs = vec![];
+        for col in 0..manifold.width {
+            let cell = manifold.grid[row][col];
+            let arriving = beams[row].contains(col);
+            glyphs.push(match cell {
+                Cell::Splitter if arriving => Glyph::SplitterHit,
+                Cell::Splitter => Glyph::SplitterUnreached,
+                Cell::Empty if arriving || beams[row + 1].contains(col) => Glyph::Beam,
+                _ => Glyph::Element(cell),
+            });
+        }
+        grid.push(glyphs);
+    }
+    return grid;
+}
+
+/**
+ * Manifold for the terminal: beams are yellow bars, splitters which were hit
+ * are green and splitters no beam reached are red.
+ */
+fn render_ansi(glyphs: &Vec<Vec<Glyph>>) -> String {
+    let mut rendered = String::new();
+    for row in glyphs {
+        for glyph in row {
+            rendered.push_str(&match glyph {
+                Glyph::Element(cell) => cell.symbol().to_string(),
+                Glyph::Beam => "\x1b[1;33m|\x1b[0m".to_string(),
+                Glyph::SplitterHit => "\x1b[1;32m^\x1b[0m".to_string(),
+                Glyph::SplitterUnreached => "\x1b[31m^\x1b[0m".to_string(),
+            });
+        }
+        rendered.push('\n');
+    }
+    return rendered;
+}
+
+// Manifold as one pixel per location, coloured as in the terminal, with empty space black
+fn pixels(glyphs: &Vec<Vec<Glyph>>) -> Vec<(u8, u8, u8)> {
+    let mut pixels = vec![];
+    for glyph in glyphs.iter().flatten() {
+        pixels.push(match glyph {
+            Glyph::Element(Cell::Empty) => (0, 0, 0),
+            Glyph::Element(Cell::Source) => (255, 255, 255),
+            Glyph::Element(Cell::Absorber) => (96, 96, 96),
+            Glyph::Element(_) => (64, 128, 255),
+            Glyph::Beam => (255, 255, 0),
+            Glyph::SplitterHit => (0, 255, 0),
+            Glyph::SplitterUnreached => (255, 0, 0),
+        });
+    }
+    return pixels;
+}
+
+/**
+ * Options:
+ *   --render[=FILE]  Draw the manifold with the beams through it, or write it as a PPM image
+ */
+fn render(manifold: &Manifold) {
+    let Some(path) = args::get_option("render") else {
+        return;
+    };
+    let glyphs = glyph_grid(manifold, &trace_beams(manifold));
+    if output::is_stdout(&path) {
+        print!("{}", render_ansi(&glyphs));
+    } else {
+        output::write_ppm(&path, manifold.width, manifold.height, &pixels(&glyphs));
+    }
+}
+
+pub fn part1(lines: Vec<String>) {
+    let manifold = load_manifold(lines);
+    render(&manifold);
+    println!("Total splits: {}", count_splits(&manifold));
+}
+
+// Timelines leaving the bottom of each column, and reaching each splitter
+struct TimelineFlow {
+    exits: Vec<u128>,
+    splitters: Vec<(usize, usize, u128)>,
+}
+
+/**
+ * Follow the timelines a particle from any of the sources could take to the
+ * bottom of the manifold, carrying the number of timelines reaching each
+ * column down a row at a time. Absorbed beams, and beams sent off the sides,
+ * end their timelines.
+ */
+fn timeline_flow(manifold: &Manifold) -> Result<TimelineFlow, String> {
+    let mut counts: Vec<u128> = vec![0; manifold.width];
+    let mut splitters = vec![];
+    for row in 0..manifold.height {
+        let mut new_counts: Vec<u128> = vec![0; manifold.width];
+        for col in 0..manifold.width {
+            match manifold.grid[row][col] {
+                Cell::Source => {
+                    new_counts[col] = new_counts[col]
+                        .checked_add(1)
+                        .ok_or(format!("Timeline count overflows at row {}", row + 1))?;
+                }
+                Cell::Splitter => splitters.push((row, col, counts[col])),
+                _ => (),
+            }
+            if counts[col] == 0 {
+                continue;
             }
-            let mut sum = 0;
-            for split_pos in split_positions {
-                sum += count_timelines(split_pos, &map, known);
+            for target in manifold.next_columns(row, col) {
+                new_counts[target] = new_counts[target]
+                    .checked_add(counts[col])
+                    .ok_or(format!("Timeline count overflows at row {}", row + 1))?;
             }
-            known.insert(start, sum);
-            return sum;
         }
+        counts = new_counts;
+        if DEBUG_OUTPUT {
+            println!("Row {}: {:?}", row + 1, counts);
+        }
+    }
+    return Ok(TimelineFlow {
+        exits: counts,
+        splitters,
+    });
+}
+
+// Total timelines leaving the bottom of the manifold
+fn total_timelines(flow: &TimelineFlow) -> Result<u128, String> {
+    let mut total: u128 = 0;
+    for count in &flow.exits {
+        total = total
+            .checked_add(*count)
+            .ok_or("Total timeline count overflows".to_string())?;
+    }
+    return Ok(total);
+}
+
+const REPORT_HEADER: [&str; 4] = ["element", "row", "col", "timelines"];
+
+// One row per column the timelines exit from, then one per splitter
+fn report_rows(flow: &TimelineFlow) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    for (col, count) in flow.exits.iter().enumerate() {
+        rows.push(vec![
+            "exit".to_string(),
+            "".to_string(),
+            (col + 1).to_string(),
+            count.to_string(),
+        ]);
+    }
+    for (row, col, count) in &flow.splitters {
+        rows.push(vec![
+            "splitter".to_string(),
+            (row + 1).to_string(),
+            (col + 1).to_string(),
+            count.to_string(),
+        ]);
+    }
+    return rows;
+}
+
+/**
+ * Options:
+ *   --csv[=FILE]    Export the timelines leaving each column, and reaching each splitter, as CSV
+ *   --table[=FILE]  Export the same as an aligned table
+ */
+fn report(flow: &TimelineFlow) {
+    let rows = report_rows(flow);
+    if let Some(path) = args::get_option("csv") {
+        output::emit(&path, &output::csv(&REPORT_HEADER, &rows));
+    }
+    if let Some(path) = args::get_option("table") {
+        output::emit(&path, &output::table(&REPORT_HEADER, &rows));
     }
-    known.insert(start, 1);
-    return 1;
 }
 
 pub fn part2(lines: Vec<String>) {
-    let (start, map) = load_grid(lines);
-    let mut known: HashMap<Pos, u64> = HashMap::new();
-    let timelines = count_timelines(start, &map, &mut known);
-    println!("Timelines: {timelines}");
+    let manifold = load_manifold(lines);
+    render(&manifold);
+    let timelines = timeline_flow(&manifold).and_then(|flow| {
+        report(&flow);
+        return total_timelines(&flow);
+    });
+    match timelines {
+        Ok(timelines) => println!("Timelines: {timelines}"),
+        Err(err) => println!("Error: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn count_timelines(manifold: &Manifold) -> Result<u128, String> {
+        return total_timelines(&timeline_flow(manifold)?);
+    }
+
+    fn manifold(lines: Vec<&str>) -> Manifold {
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        return Manifold::parse(&lines, vec![-1, 1]).unwrap();
+    }
+
+    // Recursive reference, memoizing the timelines from each position a beam lands in
+    fn timelines_from(
+        row: usize,
+        col: usize,
+        manifold: &Manifold,
+        known: &mut HashMap<(usize, usize), u64>,
+    ) -> u64 {
+        if let Some(value) = known.get(&(row, col)) {
+            return *value;
+        }
+        let mut sum = 1;
+        for next_row in row + 1..manifold.height {
+            let targets = manifold.next_columns(next_row, col);
+            if targets != vec![col] {
+                sum = 0;
+                for target in targets {
+                    sum += timelines_from(next_row, target, manifold, known);
+                }
+                break;
+            }
+        }
+        known.insert((row, col), sum);
+        return sum;
+    }
+
+    fn count_timelines_memoized(manifold: &Manifold) -> u64 {
+        let mut known = HashMap::new();
+        let mut sum = 0;
+        for row in 0..manifold.height {
+            for col in 0..manifold.width {
+                if manifold.grid[row][col] == Cell::Source {
+                    sum += timelines_from(row, col, manifold, &mut known);
+                }
+            }
+        }
+        return sum;
+    }
+
+    fn example() -> Vec<&'static str> {
+        return vec![
+            ".......S.......",
+            "...............",
+            ".......^.......",
+            "...............",
+            "......^.^......",
+            "...............",
+            ".....^.^.^.....",
+            "...............",
+            "....^.^...^....",
+            "...............",
+            "...^.^...^.^...",
+            "...............",
+            "..^...^.....^..",
+            "...............",
+            ".^.^.^.^.^...^.",
+            "...............",
+        ];
+    }
+
+    #[test]
+    fn test_count_splits() {
+        assert_eq!(count_splits(&manifold(example())), 21);
+
+        // Two splitters feeding the same column only make one beam below it
+        let lines = vec![
+            "..S...S..",
+            "..^...^..",
+            ".........",
+            "...^.^...",
+            "....^....",
+        ];
+        assert_eq!(count_splits(&manifold(lines)), 5);
+
+        // Wide enough to need more than one word per row
+        let mut wide = [".".repeat(200), ".".repeat(200)];
+        wide[0].replace_range(130..131, "S");
+        wide[1].replace_range(130..131, "^");
+        assert_eq!(
+            count_splits(&manifold(wide.iter().map(|line| line.as_str()).collect())),
+            1
+        );
+    }
+
+    #[test]
+    fn test_count_timelines() {
+        assert_eq!(count_timelines(&manifold(example())), Ok(40));
+
+        // Splitters at the edges lose the beam that would leave the manifold
+        let lines = vec!["S..", "^..", ".^.", "..^"];
+        assert_eq!(count_timelines(&manifold(lines.clone())), Ok(2));
+        assert_eq!(count_timelines_memoized(&manifold(lines)), 2);
+    }
+
+    #[test]
+    fn test_count_timelines_memoized() {
+        // Compared against the recursive version
+        let cases = [
+            example(),
+            // Splitters next to each other, and at both edges
+            vec!["..S..", "..^..", ".^^^.", "^...^", "....."],
+            // Sources in different rows, one beneath an existing beam
+            vec!["S....", ".^...", "..S..", "..^.S", "....^"],
+            // Absorbers, including one fed by a splitter
+            vec!["..S..", "..^..", ".#...", "...^.", "..#.."],
+            // Deflectors into splitters, and off the edges
+            vec!["S.S.S", "<.>.>", ".^...", "<..^.", "....."],
+            // Nothing in the way
+            vec![".S.", "...", "..."],
+        ];
+        for lines in cases {
+            let manifold = manifold(lines);
+            assert_eq!(
+                count_timelines(&manifold),
+                Ok(u128::from(count_timelines_memoized(&manifold)))
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_timelines_overflow() {
+        // Each pair of rows splits the beam and brings both halves back to the middle
+        let mut lines = vec![".S."];
+        for _ in 0..127 {
+            lines.push(".^.");
+            lines.push("^.^");
+        }
+        assert_eq!(count_timelines(&manifold(lines.clone())), Ok(1 << 127));
+
+        lines.push(".^.");
+        lines.push("^.^");
+        assert_eq!(
+            count_timelines(&manifold(lines)),
+            Err("Timeline count overflows at row 257".to_string())
+        );
+    }
+
+    #[test]
+    fn test_elements() {
+        // Each source adds its own beam and timelines
+        let lines = vec!["S...S", ".....", "^...^"];
+        assert_eq!(count_splits(&manifold(lines.clone())), 2);
+        assert_eq!(count_timelines(&manifold(lines)), Ok(2));
+
+        // Absorbers end the beam, deflectors move it without splitting
+        let lines = vec![".S.S.", ".#.>.", ".^..^", "....."];
+        assert_eq!(count_splits(&manifold(lines.clone())), 1);
+        assert_eq!(count_timelines(&manifold(lines)), Ok(1));
+        let lines = vec!["..S..", "..<..", ".^...", "....."];
+        assert_eq!(count_splits(&manifold(lines.clone())), 1);
+        assert_eq!(count_timelines(&manifold(lines)), Ok(2));
+
+        // A three-way splitter also lets the beam carry straight on
+        let lines: Vec<String> = example().iter().map(|line| line.to_string()).collect();
+        let three_way = Manifold::parse(&lines, parse_split("-1,0,1").unwrap()).unwrap();
+        assert_eq!(
+            count_timelines(&three_way),
+            Ok(u128::from(count_timelines_memoized(&three_way)))
+        );
+        assert!(count_timelines(&three_way).unwrap() > 40);
+        assert_eq!(parse_split("1, -1, 1"), Ok(vec![1, -1]));
+        assert!(parse_split("1,x").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let lines = vec!["..S..".to_string(), "..x..".to_string()];
+        assert_eq!(
+            Manifold::parse(&lines, vec![-1, 1]).err(),
+            Some("Line 2, column 3: Unknown element 'x'".to_string())
+        );
+
+        // Short rows are padded with empty space
+        let manifold = manifold(vec!["..S..", "", "..^"]);
+        assert_eq!(manifold.width, 5);
+        assert_eq!(manifold.grid[1], vec![Cell::Empty; 5]);
+        assert_eq!(count_timelines(&manifold), Ok(2));
+    }
+
+    #[test]
+    fn test_timeline_flow() {
+        let flow = timeline_flow(&manifold(example())).unwrap();
+        assert_eq!(
+            flow.exits,
+            vec![1, 0, 2, 0, 10, 0, 11, 0, 11, 0, 2, 1, 1, 0, 1]
+        );
+        assert_eq!(flow.splitters.len(), 22);
+        assert_eq!(flow.splitters[0], (2, 7, 1));
+        // Only one splitter is never reached
+        let unreached: Vec<(usize, usize)> = flow
+            .splitters
+            .iter()
+            .filter(|(_, _, count)| *count == 0)
+            .map(|(row, col, _)| (*row, *col))
+            .collect();
+        assert_eq!(unreached, vec![(14, 9)]);
+
+        let flow = timeline_flow(&manifold(vec!["S..", "^.#"])).unwrap();
+        assert_eq!(
+            output::table(&REPORT_HEADER, &report_rows(&flow)),
+            "element  | row | col | timelines\n".to_string()
+                + "exit     |     | 1   | 0\n"
+                + "exit     |     | 2   | 1\n"
+                + "exit     |     | 3   | 0\n"
+                + "splitter | 2   | 1   | 1\n"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let small = manifold(vec!["..S..", "..^..", "....^", "^...."]);
+        let glyphs = glyph_grid(&small, &trace_beams(&small));
+        let plain: Vec<String> = glyphs
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|glyph| match glyph {
+                        Glyph::Element(cell) => cell.symbol(),
+                        Glyph::Beam => '|',
+                        Glyph::SplitterHit => 'H',
+                        Glyph::SplitterUnreached => 'U',
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(plain, vec!["..S..", ".|H|.", ".|.|U", "U|.|."]);
+        assert!(render_ansi(&glyphs).starts_with("..S..\n.\x1b[1;33m|\x1b[0m\x1b[1;32m^\x1b[0m"));
+        assert_eq!(pixels(&glyphs).len(), 20);
+
+        // Every splitter drawn as hit was counted as a split
+        let example = manifold(example());
+        let glyphs = glyph_grid(&example, &trace_beams(&example));
+        let hits = glyphs
+            .iter()
+            .flatten()
+            .filter(|glyph| **glyph == Glyph::SplitterHit)
+            .count();
+        assert_eq!(hits as u64, count_splits(&example));
+    }
 }
@@ -50,3 +50,11 @@ pub fn run(day: u8, part: u8) {
         _ => println!("Implementation not available"),
     }
 }
+
+// Commands read their own input, so they can respond to it as it arrives
+pub fn run_command(day: u8, command: &str) {
+    match (day, command) {
+        (5, "query") => day05::query(),
+        _ => println!("Command not available"),
+    }
+}
@@ -2,13 +2,22 @@ mod args;
 mod days;
 mod lines;
 mod num;
+mod output;
 
 /**
  * Usage: cargo run DAY PART < INPUT_FILE
  * E.g.: cargo run 1 2 < day01_full.txt
+ *
+ * Some days accept extra options after PART, e.g.: cargo run 2 2 --list
+ *
+ * Some days also have commands, used in place of PART, e.g.:
+ * cargo run 5 query day05_full.txt < queries.txt
  */
 
 fn main() {
     let (day, part) = args::get_day_part();
-    days::run(day, part);
+    match args::get_command() {
+        Some(command) => days::run_command(day, &command),
+        None => days::run(day, part),
+    }
 }
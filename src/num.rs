@@ -9,3 +9,33 @@ pub fn char_parse_int(ch: &char) -> i32 {
     num.push(*ch);
     return parse_int(&num);
 }
+
+/**
+ * Format a number in the given radix (2-36), using lowercase letters for digits above 9
+ */
+pub fn to_radix_string(mut num: u64, radix: u32) -> String {
+    if num == 0 {
+        return "0".to_string();
+    }
+    let mut digits = vec![];
+    while num > 0 {
+        let digit = (num % u64::from(radix)) as u32;
+        digits.push(char::from_digit(digit, radix).expect("Radix must be 2-36"));
+        num /= u64::from(radix);
+    }
+    return digits.iter().rev().collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_radix_string() {
+        assert_eq!(to_radix_string(0, 2), "0");
+        assert_eq!(to_radix_string(10, 2), "1010");
+        assert_eq!(to_radix_string(255, 16), "ff");
+        assert_eq!(to_radix_string(1234, 10), "1234");
+        assert_eq!(to_radix_string(35, 36), "z");
+    }
+}
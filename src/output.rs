@@ -0,0 +1,77 @@
+use std::fs;
+
+// An empty path, or "-", means stdout
+pub fn is_stdout(path: &str) -> bool {
+    return path == "" || path == "-";
+}
+
+/**
+ * Print contents to stdout, or write them to a file if a path is given
+ */
+pub fn emit(path: &str, contents: &str) {
+    if is_stdout(path) {
+        print!("{contents}");
+        return;
+    }
+    fs::write(path, contents).expect("Failed to write output file");
+    println!("Wrote {path}");
+}
+
+/**
+ * Rows of fields as CSV, quoting fields which contain commas or quotes
+ */
+pub fn csv(header: &[&str], rows: &Vec<Vec<String>>) -> String {
+    let mut csv = header.join(",") + "\n";
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|field| {
+                if field.contains(',') || field.contains('"') {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.clone()
+                }
+            })
+            .collect();
+        csv.push_str(&(fields.join(",") + "\n"));
+    }
+    return csv;
+}
+
+/**
+ * Rows of fields as a table, with columns aligned and separated by bars
+ */
+pub fn table(header: &[&str], rows: &Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|heading| heading.len()).collect();
+    for row in rows {
+        for (col, field) in row.iter().enumerate() {
+            widths[col] = widths[col].max(field.len());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|heading| heading.to_string()).collect();
+    let mut table = String::new();
+    for row in [header].iter().chain(rows) {
+        let mut line = String::new();
+        for (col, field) in row.iter().enumerate() {
+            if col > 0 {
+                line.push_str(" | ");
+            }
+            line.push_str(&format!("{:width$}", field, width = widths[col]));
+        }
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    return table;
+}
+
+/**
+ * Write an image as a binary PPM file, with pixels given row by row
+ */
+pub fn write_ppm(path: &str, width: usize, height: usize, pixels: &Vec<(u8, u8, u8)>) {
+    let mut data = format!("P6\n{width} {height}\n255\n").into_bytes();
+    for (red, green, blue) in pixels {
+        data.extend([*red, *green, *blue]);
+    }
+    fs::write(path, data).expect("Failed to write image file");
+    println!("Wrote {path}");
+}